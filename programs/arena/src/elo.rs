@@ -0,0 +1,144 @@
+//! Deterministic integer ELO
+//!
+//! Pure integer math, so the on-chain result can be reproduced bit-for-bit
+//! off-chain (e.g. by a matchmaking service predicting rating changes).
+//! Scores and expectations are fixed-point in units of 1/`SCALE`.
+
+/// Fixed-point scale for scores and expected scores
+pub const SCALE: i64 = 10_000;
+
+/// Score for a win, draw and loss
+pub const WIN: i64 = SCALE;
+pub const DRAW: i64 = SCALE / 2;
+pub const LOSS: i64 = 0;
+
+pub const K_FACTOR: i64 = 32;
+
+/// Ratings never drop below this floor
+pub const MIN_RATING: u32 = 100;
+
+/// Rating difference between table entries
+const STEP: i64 = 25;
+
+/// Expected score of the higher-rated side for a rating difference of
+/// `i * STEP`: round(SCALE / (1 + 10^(-i * STEP / 400))). Differences beyond
+/// the last entry are clamped.
+const EXPECTED: [i64; 33] = [
+    5000, 5359, 5715, 6063, 6401, 6725, 7034, 7325, 7597, 7850, 8083, 8296, 8490, 8666, 8823,
+    8965, 9091, 9203, 9302, 9390, 9468, 9536, 9595, 9648, 9693, 9733, 9768, 9799, 9825, 9848,
+    9868, 9886, 9901,
+];
+
+/// Expected score of `rating` against `opponent`, in 1/`SCALE` units
+/// (linear interpolation between table entries)
+pub fn expected_score(rating: u32, opponent: u32) -> i64 {
+    let diff = rating as i64 - opponent as i64;
+    let max_diff = STEP * (EXPECTED.len() as i64 - 1);
+    let abs = diff.abs().min(max_diff);
+
+    let index = (abs / STEP) as usize;
+    let remainder = abs % STEP;
+    let expected = if remainder == 0 {
+        EXPECTED[index]
+    } else {
+        EXPECTED[index] + (EXPECTED[index + 1] - EXPECTED[index]) * remainder / STEP
+    };
+
+    if diff >= 0 {
+        expected
+    } else {
+        SCALE - expected
+    }
+}
+
+/// Rating change for `rating` against `opponent` given `score` (WIN, DRAW or LOSS),
+/// rounded to the nearest point (half away from zero)
+pub fn rating_change(rating: u32, opponent: u32, score: i64) -> i64 {
+    let scaled = K_FACTOR * (score - expected_score(rating, opponent));
+    let half = SCALE / 2;
+    if scaled >= 0 {
+        (scaled + half) / SCALE
+    } else {
+        (scaled - half) / SCALE
+    }
+}
+
+/// New ratings for A and B given A's score (WIN, DRAW or LOSS)
+pub fn update(elo_a: u32, elo_b: u32, score_a: i64) -> (u32, u32) {
+    let change_a = rating_change(elo_a, elo_b, score_a);
+    let change_b = rating_change(elo_b, elo_a, SCALE - score_a);
+    (apply(elo_a, change_a), apply(elo_b, change_b))
+}
+
+fn apply(rating: u32, change: i64) -> u32 {
+    (rating as i64 + change).max(MIN_RATING as i64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_matches_formula() {
+        for (i, &entry) in EXPECTED.iter().enumerate() {
+            let diff = (i as i64 * STEP) as f64;
+            let expected = SCALE as f64 / (1.0 + 10f64.powf(-diff / 400.0));
+            assert_eq!(entry, expected.round() as i64, "entry {}", i);
+        }
+    }
+
+    #[test]
+    fn expected_at_steps() {
+        assert_eq!(expected_score(1200, 1200), 5000);
+        assert_eq!(expected_score(1225, 1200), EXPECTED[1]);
+        assert_eq!(expected_score(1400, 1200), EXPECTED[8]);
+    }
+
+    #[test]
+    fn expected_interpolates_between_steps() {
+        // 10 points is 2/5 of the way from EXPECTED[0] to EXPECTED[1]
+        assert_eq!(expected_score(1210, 1200), 5000 + (5359 - 5000) * 10 / 25);
+        assert_eq!(expected_score(1237, 1200), 5359 + (5715 - 5359) * 12 / 25);
+    }
+
+    #[test]
+    fn expected_is_symmetric() {
+        for (a, b) in [(1200, 1200), (1210, 1200), (1500, 1237), (100, 2900), (1800, 1000)] {
+            assert_eq!(expected_score(a, b) + expected_score(b, a), SCALE, "{} vs {}", a, b);
+        }
+    }
+
+    #[test]
+    fn expected_clamps_past_800_points() {
+        assert_eq!(expected_score(2000, 1200), EXPECTED[32]);
+        assert_eq!(expected_score(3000, 1200), EXPECTED[32]);
+        assert_eq!(expected_score(1200, 3000), SCALE - EXPECTED[32]);
+    }
+
+    #[test]
+    fn rating_change_rounds_half_away_from_zero() {
+        // Equal ratings: +-16 exactly
+        assert_eq!(rating_change(1200, 1200, WIN), 16);
+        assert_eq!(rating_change(1200, 1200, LOSS), -16);
+        assert_eq!(rating_change(1200, 1200, DRAW), 0);
+        // 32 * (0 - 5359) / 10000 = -17.1488 -> -17
+        assert_eq!(rating_change(1225, 1200, LOSS), -17);
+        // 32 * (5000 - 7850) / 10000 = -9.12 -> -9
+        assert_eq!(rating_change(1425, 1200, DRAW), -9);
+        // 32 * (0 - 5715) / 10000 = -18.288 -> -18; 32 * (10000 - 4285) / 10000 = 18.288 -> 18
+        assert_eq!(rating_change(1250, 1200, LOSS), -18);
+        assert_eq!(rating_change(1200, 1250, WIN), 18);
+    }
+
+    #[test]
+    fn update_is_zero_sum_for_equal_ratings() {
+        assert_eq!(update(1200, 1200, WIN), (1216, 1184));
+        assert_eq!(update(1200, 1200, DRAW), (1200, 1200));
+    }
+
+    #[test]
+    fn update_respects_min_rating() {
+        assert_eq!(update(MIN_RATING, 1200, LOSS).0, MIN_RATING);
+        assert_eq!(update(MIN_RATING + 5, MIN_RATING + 5, LOSS).0, MIN_RATING);
+    }
+}
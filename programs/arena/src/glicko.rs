@@ -0,0 +1,204 @@
+//! Glicko-2 ratings
+//!
+//! Tracks rating confidence (deviation) and consistency (volatility) alongside
+//! ELO. Each battle is treated as a one-game rating period; deviation also
+//! grows for every full `RATING_PERIOD` a fighter sits idle.
+//! Reference: Glickman, "Example of the Glicko-2 system".
+//!
+//! Applied by the `update_glicko` crank rather than inside settlement, so the
+//! float iterations (capped below) never hold up payouts. This is f64 math and
+//! is not guaranteed bit-for-bit reproducible off-chain; the integer `elo`
+//! rating is the reproducible one.
+
+pub const DEFAULT_RATING: f64 = 1500.0;
+pub const DEFAULT_DEVIATION: f64 = 350.0;
+pub const DEFAULT_VOLATILITY: f64 = 0.06;
+
+/// Idle time after which deviation grows by one period's volatility
+pub const RATING_PERIOD: i64 = 86_400;
+
+/// Constrains volatility change over time
+const TAU: f64 = 0.5;
+/// Convergence tolerance for the volatility iteration
+const EPSILON: f64 = 0.000_001;
+/// Iteration caps that bound compute on-chain; on hitting one, volatility is left unchanged
+const MAX_BRACKET_STEPS: u32 = 32;
+const MAX_ITERATIONS: u32 = 64;
+/// Conversion between the Glicko and Glicko-2 scales
+const SCALE: f64 = 173.7178;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self {
+            rating: DEFAULT_RATING,
+            deviation: DEFAULT_DEVIATION,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+}
+
+/// Grow deviation for `idle_periods` rating periods without games (capped at the default)
+pub fn decay(player: Rating, idle_periods: i64) -> Rating {
+    if idle_periods <= 0 {
+        return player;
+    }
+    let phi = player.deviation / SCALE;
+    let phi = (phi * phi + idle_periods as f64 * player.volatility * player.volatility).sqrt();
+    Rating {
+        deviation: (phi * SCALE).min(DEFAULT_DEVIATION),
+        ..player
+    }
+}
+
+/// Rate `player` after one game against `opponent`; `score` is 1.0, 0.5 or 0.0
+pub fn update(player: Rating, opponent: Rating, score: f64) -> Rating {
+    update_period(player, &[(opponent, score)])
+}
+
+/// Rate `player` after a rating period of `games` (opponent, score)
+pub fn update_period(player: Rating, games: &[(Rating, f64)]) -> Rating {
+    let mu = (player.rating - DEFAULT_RATING) / SCALE;
+    let phi = player.deviation / SCALE;
+    let sigma = player.volatility;
+
+    let mut v_inv = 0.0;
+    let mut improvement = 0.0;
+    for (opponent, score) in games {
+        let mu_j = (opponent.rating - DEFAULT_RATING) / SCALE;
+        let phi_j = opponent.deviation / SCALE;
+        let g = 1.0 / (1.0 + 3.0 * phi_j * phi_j / (std::f64::consts::PI * std::f64::consts::PI)).sqrt();
+        let expected = 1.0 / (1.0 + (-g * (mu - mu_j)).exp());
+        v_inv += g * g * expected * (1.0 - expected);
+        improvement += g * (score - expected);
+    }
+    let v = 1.0 / v_inv;
+    let delta = v * improvement;
+
+    let new_sigma = new_volatility(phi, sigma, v, delta);
+    let phi_star = (phi * phi + new_sigma * new_sigma).sqrt();
+    let new_phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+    let new_mu = mu + new_phi * new_phi * improvement;
+
+    Rating {
+        rating: new_mu * SCALE + DEFAULT_RATING,
+        deviation: (new_phi * SCALE).min(DEFAULT_DEVIATION),
+        volatility: new_sigma,
+    }
+}
+
+/// Step 5 of Glicko-2: solve for the new volatility (Illinois algorithm)
+/// Iterations are capped; if either loop fails to finish (or the result isn't finite) the
+/// old volatility is kept, so settlement can never run out of compute here.
+fn new_volatility(phi: f64, sigma: f64, v: f64, delta: f64) -> f64 {
+    let a = (sigma * sigma).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        let denom = phi * phi + v + ex;
+        ex * (delta * delta - phi * phi - v - ex) / (2.0 * denom * denom) - (x - a) / (TAU * TAU)
+    };
+
+    let mut big_a = a;
+    let mut big_b = if delta * delta > phi * phi + v {
+        (delta * delta - phi * phi - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * TAU) < 0.0 {
+            if k as u32 >= MAX_BRACKET_STEPS {
+                return sigma;
+            }
+            k += 1.0;
+        }
+        a - k * TAU
+    };
+
+    let mut f_a = f(big_a);
+    let mut f_b = f(big_b);
+    let mut iterations = 0;
+    while (big_b - big_a).abs() > EPSILON {
+        iterations += 1;
+        if iterations > MAX_ITERATIONS {
+            return sigma;
+        }
+        let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+        let f_c = f(big_c);
+        if f_c * f_b <= 0.0 {
+            big_a = big_b;
+            f_a = f_b;
+        } else {
+            f_a /= 2.0;
+        }
+        big_b = big_c;
+        f_b = f_c;
+    }
+
+    let new_sigma = (big_a / 2.0).exp();
+    if new_sigma.is_finite() {
+        new_sigma
+    } else {
+        sigma
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rating: f64, deviation: f64) -> Rating {
+        Rating {
+            rating,
+            deviation,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+
+    #[test]
+    fn glickman_worked_example() {
+        let player = rating(1500.0, 200.0);
+        let games = [
+            (rating(1400.0, 30.0), 1.0),
+            (rating(1550.0, 100.0), 0.0),
+            (rating(1700.0, 300.0), 0.0),
+        ];
+        let new = update_period(player, &games);
+        assert!((new.rating - 1464.06).abs() < 0.01, "rating {}", new.rating);
+        assert!((new.deviation - 151.52).abs() < 0.01, "deviation {}", new.deviation);
+        assert!((new.volatility - 0.05999).abs() < 0.00001, "volatility {}", new.volatility);
+    }
+
+    #[test]
+    fn single_game_win_and_loss_are_mirrored() {
+        let a = Rating::default();
+        let won = update(a, a, 1.0);
+        let lost = update(a, a, 0.0);
+        assert!(won.rating > DEFAULT_RATING && lost.rating < DEFAULT_RATING);
+        assert!((won.rating - DEFAULT_RATING - (DEFAULT_RATING - lost.rating)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn extreme_inputs_terminate_and_stay_finite() {
+        let cases = [
+            (rating(5000.0, 1.0), rating(-5000.0, 350.0), 0.0),
+            (rating(100.0, 350.0), rating(3000.0, 0.01), 1.0),
+            (Rating { volatility: 5.0, ..rating(1500.0, 350.0) }, rating(1500.0, 350.0), 0.5),
+        ];
+        for (player, opponent, score) in cases {
+            let new = update(player, opponent, score);
+            assert!(new.rating.is_finite() && new.deviation.is_finite() && new.volatility.is_finite());
+        }
+    }
+
+    #[test]
+    fn decay_grows_deviation_up_to_default() {
+        let player = rating(1500.0, 50.0);
+        assert_eq!(decay(player, 0), player);
+        assert!(decay(player, 1).deviation > 50.0);
+        assert_eq!(decay(player, 1_000_000).deviation, DEFAULT_DEVIATION);
+    }
+}
@@ -1,7 +1,39 @@
+#![allow(clippy::result_large_err)]
+
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::spl_token_2022::extension::{
+    transfer_fee::{instruction::harvest_withheld_tokens_to_mint, TransferFeeAmount},
+    BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface};
+
+pub mod elo;
+pub mod glicko;
 
 declare_id!("6fh5E6VPXzAww1mU9M84sBgtqUXDDVY9HZh47tGBFCKb");
 
+/// House fee cap (10%)
+pub const MAX_HOUSE_FEE_BPS: u16 = 1000;
+
+/// Pause bits for `Arena.paused`. Refund paths (cancel, decline, expire, claim_refund) are never paused.
+pub const PAUSE_CHALLENGE: u8 = 1 << 0;
+pub const PAUSE_ACCEPT: u8 = 1 << 1;
+pub const PAUSE_BET: u8 = 1 << 2;
+pub const PAUSE_VOTE: u8 = 1 << 3;
+pub const PAUSE_SETTLE: u8 = 1 << 4;
+pub const PAUSE_CLAIM: u8 = 1 << 5;
+pub const PAUSE_ALL: u8 = PAUSE_CHALLENGE | PAUSE_ACCEPT | PAUSE_BET | PAUSE_VOTE | PAUSE_SETTLE | PAUSE_CLAIM;
+
+/// Share of committed votes that must be revealed for the tally to count (else a draw)
+pub const REVEAL_QUORUM_BPS: u64 = 5000;
+
+/// Permission bits for a fighter's delegate (`SessionKey.permissions`)
+pub const DELEGATE_CHALLENGE: u8 = 1 << 0;
+pub const DELEGATE_ACCEPT: u8 = 1 << 1;
+pub const DELEGATE_CANCEL: u8 = 1 << 2;
+pub const DELEGATE_ALL: u8 = DELEGATE_CHALLENGE | DELEGATE_ACCEPT | DELEGATE_CANCEL;
+
 /// Agent Battle Arena - FULLY AUTONOMOUS
 /// 
 /// Agents challenge each other. Anyone bets. Votes decide winners.
@@ -15,21 +47,308 @@ pub mod arena {
 
     /// Initialize the arena with config
     pub fn initialize(ctx: Context<Initialize>, config: ArenaConfig) -> Result<()> {
+        validate_config(&config)?;
+        
         let arena = &mut ctx.accounts.arena;
         arena.authority = ctx.accounts.authority.key();
+        arena.pending_authority = None;
+        arena.paused = 0;
         arena.house_fee_bps = config.house_fee_bps;
         arena.min_bet = config.min_bet;
         arena.min_stake_to_create = config.min_stake_to_create;
         arena.voting_period = config.voting_period;
+        arena.acceptance_period = config.acceptance_period;
+        arena.reveal_period = config.reveal_period;
+        arena.claim_period = config.claim_period;
+        arena.bet_cutoff = config.bet_cutoff;
+        arena.min_judge_battles = config.min_judge_battles;
+        arena.min_judge_elo = config.min_judge_elo;
+        arena.max_missed_reveals = config.max_missed_reveals;
         arena.treasury = ctx.accounts.treasury.key();
         arena.battle_count = 0;
+        arena.battles_settled = 0;
         arena.total_volume = 0;
+        arena.total_fees_collected = 0;
+        arena.total_paid_out = 0;
+        arena.total_swept = 0;
+        arena.claim_pause_total = 0;
+        arena.claim_paused_at = None;
         arena.bump = *ctx.bumps.get("arena").unwrap();
         
+        emit!(ArenaInitialized {
+            authority: arena.authority,
+            treasury: arena.treasury,
+            house_fee_bps: arena.house_fee_bps,
+            min_bet: arena.min_bet,
+            min_stake_to_create: arena.min_stake_to_create,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
         msg!("Arena initialized. Fully autonomous mode.");
         Ok(())
     }
 
+    /// Update arena config and treasury (authority only)
+    pub fn update_config(ctx: Context<UpdateConfig>, config: ArenaConfig) -> Result<()> {
+        validate_config(&config)?;
+        
+        let arena = &mut ctx.accounts.arena;
+        arena.house_fee_bps = config.house_fee_bps;
+        arena.min_bet = config.min_bet;
+        arena.min_stake_to_create = config.min_stake_to_create;
+        arena.voting_period = config.voting_period;
+        arena.acceptance_period = config.acceptance_period;
+        arena.reveal_period = config.reveal_period;
+        arena.claim_period = config.claim_period;
+        arena.bet_cutoff = config.bet_cutoff;
+        arena.min_judge_battles = config.min_judge_battles;
+        arena.min_judge_elo = config.min_judge_elo;
+        arena.max_missed_reveals = config.max_missed_reveals;
+        arena.treasury = ctx.accounts.treasury.key();
+        
+        emit!(ArenaConfigUpdated {
+            authority: ctx.accounts.authority.key(),
+            treasury: arena.treasury,
+            house_fee_bps: arena.house_fee_bps,
+            min_bet: arena.min_bet,
+            min_stake_to_create: arena.min_stake_to_create,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Arena config updated");
+        Ok(())
+    }
+
+    /// Upgrade an arena created before the current layout in place (authority only)
+    /// Reallocs the account, keeps authority, treasury and counters, and applies `config`.
+    pub fn migrate_arena(ctx: Context<MigrateArena>, config: ArenaConfig) -> Result<()> {
+        validate_config(&config)?;
+        
+        let arena_info = ctx.accounts.arena.to_account_info();
+        let new_len = 8 + Arena::INIT_SPACE;
+        let legacy = {
+            let data = arena_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == Arena::discriminator(),
+                ArenaError::InvalidArenaAccount
+            );
+            require!(data.len() < new_len, ArenaError::AlreadyMigrated);
+            LegacyArena::deserialize(&mut &data[8..])?
+        };
+        require!(legacy.authority == ctx.accounts.authority.key(), ArenaError::NotArenaAuthority);
+        
+        let rent = Rent::get()?.minimum_balance(new_len);
+        if arena_info.lamports() < rent {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: arena_info.clone(),
+                    },
+                ),
+                rent - arena_info.lamports(),
+            )?;
+        }
+        arena_info.realloc(new_len, false)?;
+        
+        let arena = Arena {
+            authority: legacy.authority,
+            pending_authority: None,
+            paused: 0,
+            treasury: legacy.treasury,
+            house_fee_bps: config.house_fee_bps,
+            min_bet: config.min_bet,
+            min_stake_to_create: config.min_stake_to_create,
+            voting_period: config.voting_period,
+            acceptance_period: config.acceptance_period,
+            reveal_period: config.reveal_period,
+            claim_period: config.claim_period,
+            bet_cutoff: config.bet_cutoff,
+            min_judge_battles: config.min_judge_battles,
+            min_judge_elo: config.min_judge_elo,
+            max_missed_reveals: config.max_missed_reveals,
+            battle_count: legacy.battle_count,
+            battles_settled: 0,
+            total_volume: legacy.total_volume,
+            total_fees_collected: 0,
+            total_paid_out: 0,
+            total_swept: 0,
+            claim_pause_total: 0,
+            claim_paused_at: None,
+            bump: legacy.bump,
+        };
+        arena.try_serialize(&mut &mut arena_info.try_borrow_mut_data()?[..])?;
+        
+        emit!(ArenaMigrated {
+            authority: arena.authority,
+            size: new_len as u32,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Arena migrated to {} bytes", new_len);
+        Ok(())
+    }
+
+    /// Upgrade a fighter registered before the current account layout, in place (wallet only)
+    /// Keeps name, record, ELO and earnings; new fields start at registration defaults and
+    /// the wallet pays for the extra space.
+    pub fn migrate_fighter(ctx: Context<MigrateFighter>) -> Result<()> {
+        let fighter_info = ctx.accounts.fighter.to_account_info();
+        let new_len = 8 + Fighter::INIT_SPACE;
+        let legacy = {
+            let data = fighter_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == Fighter::discriminator(),
+                ArenaError::InvalidFighterAccount
+            );
+            require!(data.len() < new_len, ArenaError::AlreadyMigrated);
+            LegacyFighter::deserialize(&mut &data[8..])?
+        };
+        require!(legacy.wallet == ctx.accounts.wallet.key(), ArenaError::InvalidFighterAccount);
+        
+        let rent = Rent::get()?.minimum_balance(new_len);
+        if fighter_info.lamports() < rent {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.wallet.to_account_info(),
+                        to: fighter_info.clone(),
+                    },
+                ),
+                rent - fighter_info.lamports(),
+            )?;
+        }
+        fighter_info.realloc(new_len, false)?;
+        
+        let now = Clock::get()?.unix_timestamp;
+        let fighter = Fighter {
+            wallet: legacy.wallet,
+            name: legacy.name,
+            elo: legacy.elo,
+            wins: legacy.wins,
+            losses: legacy.losses,
+            draws: legacy.draws,
+            total_earnings: legacy.total_earnings,
+            total_losses: 0,
+            registered_at: legacy.registered_at,
+            glicko_rating: glicko::DEFAULT_RATING,
+            glicko_deviation: glicko::DEFAULT_DEVIATION,
+            glicko_volatility: glicko::DEFAULT_VOLATILITY,
+            last_played: now,
+            metadata_uri: String::new(),
+            endpoint: String::new(),
+            model: String::new(),
+            avatar_hash: [0; 32],
+            active_battles: 0,
+            delegate: None,
+            missed_reveals: 0,
+            bump: legacy.bump,
+        };
+        fighter.try_serialize(&mut &mut fighter_info.try_borrow_mut_data()?[..])?;
+        
+        emit!(FighterMigrated {
+            fighter: fighter_info.key(),
+            wallet: fighter.wallet,
+            size: new_len as u32,
+            timestamp: now,
+        });
+        
+        msg!("Fighter {} migrated to {} bytes", fighter.name, new_len);
+        Ok(())
+    }
+
+    /// Propose a new arena authority (authority only, takes effect once accepted)
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        arena.pending_authority = Some(new_authority);
+        
+        emit!(AuthorityProposed {
+            authority: arena.authority,
+            pending_authority: new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Authority transfer proposed to {}", new_authority);
+        Ok(())
+    }
+
+    /// Accept a proposed authority transfer (pending authority only)
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        require!(
+            arena.pending_authority == Some(ctx.accounts.new_authority.key()),
+            ArenaError::NotPendingAuthority
+        );
+        
+        arena.authority = ctx.accounts.new_authority.key();
+        arena.pending_authority = None;
+        
+        emit!(AuthorityTransferred {
+            new_authority: arena.authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Authority transferred to {}", arena.authority);
+        Ok(())
+    }
+
+    /// Pause or resume instructions by bitmask, e.g. PAUSE_ALL or PAUSE_SETTLE | PAUSE_CLAIM (authority only)
+    pub fn set_pause(ctx: Context<SetPause>, paused: u8) -> Result<()> {
+        require!(paused & !PAUSE_ALL == 0, ArenaError::InvalidPauseFlags);
+        
+        let arena = &mut ctx.accounts.arena;
+        let now = Clock::get()?.unix_timestamp;
+        
+        // Claim deadlines don't run while claims are paused
+        match (paused & PAUSE_CLAIM != 0, arena.claim_paused_at) {
+            (true, None) => arena.claim_paused_at = Some(now),
+            (false, Some(paused_at)) => {
+                arena.claim_pause_total += now - paused_at;
+                arena.claim_paused_at = None;
+            }
+            _ => {}
+        }
+        arena.paused = paused;
+        
+        emit!(PauseUpdated {
+            paused,
+            timestamp: now,
+        });
+        
+        msg!("Arena pause flags set to {:#08b}", paused);
+        Ok(())
+    }
+
+    /// Allow (or update) an SPL mint for battle pools with its own minimums (authority only)
+    pub fn configure_mint(
+        ctx: Context<ConfigureMint>,
+        min_bet: u64,
+        min_stake_to_create: u64,
+        enabled: bool,
+    ) -> Result<()> {
+        check_mint_extensions(&ctx.accounts.mint.to_account_info())?;
+        
+        let mint_config = &mut ctx.accounts.mint_config;
+        mint_config.mint = ctx.accounts.mint.key();
+        mint_config.min_bet = min_bet;
+        mint_config.min_stake_to_create = min_stake_to_create;
+        mint_config.enabled = enabled;
+        mint_config.bump = *ctx.bumps.get("mint_config").unwrap();
+        
+        emit!(MintConfigured {
+            mint: mint_config.mint,
+            min_bet,
+            min_stake_to_create,
+            enabled,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Mint {} configured (enabled: {})", mint_config.mint, enabled);
+        Ok(())
+    }
+
     /// Register a fighter (agent) in the arena
     pub fn register_fighter(ctx: Context<RegisterFighter>, name: String) -> Result<()> {
         require!(name.len() <= 32, ArenaError::NameTooLong);
@@ -42,34 +361,276 @@ pub mod arena {
         fighter.losses = 0;
         fighter.draws = 0;
         fighter.total_earnings = 0;
+        fighter.total_losses = 0;
         fighter.registered_at = Clock::get()?.unix_timestamp;
+        fighter.glicko_rating = glicko::DEFAULT_RATING;
+        fighter.glicko_deviation = glicko::DEFAULT_DEVIATION;
+        fighter.glicko_volatility = glicko::DEFAULT_VOLATILITY;
+        fighter.last_played = fighter.registered_at;
+        fighter.metadata_uri = String::new();
+        fighter.endpoint = String::new();
+        fighter.model = String::new();
+        fighter.avatar_hash = [0; 32];
+        fighter.active_battles = 0;
+        fighter.delegate = None;
+        fighter.missed_reveals = 0;
         fighter.bump = *ctx.bumps.get("fighter").unwrap();
         
+        emit!(FighterRegistered {
+            fighter: fighter.key(),
+            wallet: fighter.wallet,
+            name: fighter.name.clone(),
+            timestamp: fighter.registered_at,
+        });
+        
         msg!("Fighter registered: {}", fighter.name);
         Ok(())
     }
 
+    /// Update a fighter's profile (wallet only); fields left as None are unchanged
+    pub fn update_fighter(ctx: Context<UpdateFighter>, update: FighterProfileUpdate) -> Result<()> {
+        let fighter = &mut ctx.accounts.fighter;
+        
+        if let Some(name) = update.name {
+            require!(name.len() <= 32, ArenaError::NameTooLong);
+            fighter.name = name;
+        }
+        if let Some(metadata_uri) = update.metadata_uri {
+            require!(metadata_uri.len() <= 200, ArenaError::UriTooLong);
+            fighter.metadata_uri = metadata_uri;
+        }
+        if let Some(endpoint) = update.endpoint {
+            require!(endpoint.len() <= 200, ArenaError::UriTooLong);
+            fighter.endpoint = endpoint;
+        }
+        if let Some(model) = update.model {
+            require!(model.len() <= 64, ArenaError::ModelTooLong);
+            fighter.model = model;
+        }
+        if let Some(avatar_hash) = update.avatar_hash {
+            fighter.avatar_hash = avatar_hash;
+        }
+        
+        emit!(FighterUpdated {
+            fighter: fighter.key(),
+            wallet: fighter.wallet,
+            name: fighter.name.clone(),
+            metadata_uri: fighter.metadata_uri.clone(),
+            endpoint: fighter.endpoint.clone(),
+            model: fighter.model.clone(),
+            avatar_hash: fighter.avatar_hash,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Fighter updated: {}", fighter.name);
+        Ok(())
+    }
+
+    /// Retire a fighter and reclaim its rent (wallet only, no open battles)
+    pub fn close_fighter(ctx: Context<CloseFighter>) -> Result<()> {
+        let fighter = &ctx.accounts.fighter;
+        
+        require!(fighter.active_battles == 0, ArenaError::FighterHasActiveBattles);
+        
+        // Nothing can sign for the vault once the fighter is gone, so empty it to the wallet
+        let vault_refund = ctx.accounts.vault.lamports();
+        if vault_refund > 0 {
+            transfer_from_vault(
+                fighter.key(),
+                *ctx.bumps.get("vault").unwrap(),
+                ctx.accounts.vault.to_account_info(),
+                ctx.accounts.wallet.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                vault_refund,
+            )?;
+        }
+        
+        // The account is closed, so the event is the archived record
+        emit!(FighterRetired {
+            fighter: fighter.key(),
+            wallet: fighter.wallet,
+            name: fighter.name.clone(),
+            elo: fighter.elo,
+            wins: fighter.wins,
+            losses: fighter.losses,
+            draws: fighter.draws,
+            total_earnings: fighter.total_earnings,
+            total_losses: fighter.total_losses,
+            registered_at: fighter.registered_at,
+            vault_refund,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Fighter retired: {}", fighter.name);
+        Ok(())
+    }
+
+    /// Authorize a delegate (session key) to challenge, accept or cancel for this fighter (wallet only)
+    /// Delegate-signed SOL stakes come from the fighter vault (PDA ["vault", fighter]) and count
+    /// toward `spend_limit`; SPL stakes come from the owner's token account via an SPL approval.
+    pub fn set_delegate(
+        ctx: Context<UpdateFighter>,
+        key: Pubkey,
+        permissions: u8,
+        spend_limit: u64,
+        expires_at: i64,
+    ) -> Result<()> {
+        let fighter = &mut ctx.accounts.fighter;
+        
+        require!(key != fighter.wallet, ArenaError::InvalidDelegate);
+        require!(permissions != 0 && permissions & !DELEGATE_ALL == 0, ArenaError::InvalidDelegate);
+        require!(expires_at > Clock::get()?.unix_timestamp, ArenaError::InvalidDelegate);
+        
+        fighter.delegate = Some(SessionKey {
+            key,
+            permissions,
+            spend_limit,
+            spent: 0,
+            expires_at,
+        });
+        
+        emit!(DelegateUpdated {
+            fighter: fighter.key(),
+            delegate: Some(key),
+            permissions,
+            spend_limit,
+            expires_at,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Delegate {} set for {}", key, fighter.name);
+        Ok(())
+    }
+
+    /// Revoke a fighter's delegate (wallet only)
+    pub fn revoke_delegate(ctx: Context<UpdateFighter>) -> Result<()> {
+        let fighter = &mut ctx.accounts.fighter;
+        fighter.delegate = None;
+        
+        emit!(DelegateUpdated {
+            fighter: fighter.key(),
+            delegate: None,
+            permissions: 0,
+            spend_limit: 0,
+            expires_at: 0,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Delegate revoked for {}", fighter.name);
+        Ok(())
+    }
+
+    /// Withdraw lamports from the fighter vault (wallet only)
+    pub fn withdraw_vault(ctx: Context<WithdrawVault>, amount: u64) -> Result<()> {
+        let fighter_key = ctx.accounts.fighter.key();
+        transfer_from_vault(
+            fighter_key,
+            *ctx.bumps.get("vault").unwrap(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.wallet.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            amount,
+        )?;
+        
+        emit!(VaultWithdrawn {
+            fighter: fighter_key,
+            wallet: ctx.accounts.wallet.key(),
+            amount,
+            remaining: ctx.accounts.vault.lamports(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Withdrew {} lamports from vault", amount);
+        Ok(())
+    }
+
     /// Challenge another fighter to a battle (AUTONOMOUS - any fighter can challenge)
+    /// Omit the opponent and pass `open_terms` to post an open call any eligible fighter can accept
     pub fn challenge(
         ctx: Context<Challenge>,
         topic: String,
         stake: u64,
         voting_period: i64,
+        open_terms: Option<OpenTerms>,
     ) -> Result<()> {
         require!(topic.len() <= 256, ArenaError::TopicTooLong);
         let arena = &ctx.accounts.arena;
-        require!(stake >= arena.min_stake_to_create, ArenaError::StakeTooLow);
-        require!(voting_period >= 300 && voting_period <= 86400, ArenaError::InvalidVotingPeriod);
-        require!(ctx.accounts.challenger.key() != ctx.accounts.opponent.key(), ArenaError::SameFighter);
+        require!(!arena.is_paused(PAUSE_CHALLENGE), ArenaError::Paused);
+        let min_stake = match (&ctx.accounts.mint, &ctx.accounts.mint_config) {
+            (None, _) => arena.min_stake_to_create,
+            (Some(mint), Some(config)) => {
+                require!(config.mint == mint.key() && config.enabled, ArenaError::MintNotAllowed);
+                config.min_stake_to_create
+            }
+            (Some(_), None) => return err!(ArenaError::MintNotAllowed),
+        };
+        require!(stake >= min_stake, ArenaError::StakeTooLow);
+        require!((300..=86400).contains(&voting_period), ArenaError::InvalidVotingPeriod);
+        // Betting must stay open for part of this battle's own voting period
+        require!(arena.bet_cutoff < voting_period, ArenaError::InvalidBetCutoff);
         
-        // Transfer stake to escrow
-        anchor_lang::system_program::transfer(
-            CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.challenger_wallet.to_account_info(),
-                    to: ctx.accounts.escrow.to_account_info(),
-                },
+        let opponent_key = match (&ctx.accounts.opponent, &open_terms) {
+            (Some(opponent), None) => {
+                require!(ctx.accounts.challenger.key() != opponent.key(), ArenaError::SameFighter);
+                opponent.key()
+            }
+            (None, Some(terms)) => {
+                require!(terms.min_elo <= terms.max_elo, ArenaError::InvalidOpenTerms);
+                Pubkey::default()
+            }
+            _ => return err!(ArenaError::InvalidOpenTerms),
+        };
+        
+        let mint = ctx.accounts.mint.as_ref().map(|mint| mint.key());
+        let now = Clock::get()?.unix_timestamp;
+        let vault_spend = if mint.is_none() { stake } else { 0 };
+        let is_delegate = authorize_fighter(
+            &mut ctx.accounts.challenger,
+            ctx.accounts.challenger_wallet.key(),
+            DELEGATE_CHALLENGE,
+            vault_spend,
+            now,
+        )?;
+        
+        let challenger_key = ctx.accounts.challenger.key();
+        let vault_seeds = &[
+            b"vault".as_ref(),
+            challenger_key.as_ref(),
+            &[ctx.bumps.get("challenger_vault").copied().unwrap_or_default()],
+        ];
+        let (depositor, depositor_seeds) = stake_source(
+            is_delegate && mint.is_none(),
+            &ctx.accounts.challenger_wallet,
+            &ctx.accounts.challenger_vault,
+            vault_seeds,
+        )?;
+        
+        // Battle payer funds the SOL escrow's rent-exempt reserve; payouts never touch it
+        if mint.is_none() {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.challenger_wallet.to_account_info(),
+                        to: ctx.accounts.escrow.to_account_info(),
+                    },
+                ),
+                Rent::get()?.minimum_balance(0),
+            )?;
+        }
+        
+        // Transfer stake to escrow (net of any token transfer fee)
+        let stake = deposit_to_escrow(
+            mint,
+            depositor,
+            depositor_seeds,
+            ctx.accounts.escrow.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            TokenLeg::from_accounts(
+                &ctx.accounts.mint,
+                &ctx.accounts.challenger_token,
+                &ctx.accounts.escrow_token,
+                &ctx.accounts.token_program,
             ),
             stake,
         )?;
@@ -77,10 +638,10 @@ pub mod arena {
         // Create challenger's bet account
         let challenger_bet = &mut ctx.accounts.challenger_bet;
         challenger_bet.battle = ctx.accounts.battle.key();
-        challenger_bet.bettor = ctx.accounts.challenger_wallet.key();
+        challenger_bet.bettor = ctx.accounts.challenger.wallet;
+        challenger_bet.payer = ctx.accounts.challenger_wallet.key();
         challenger_bet.amount = stake;
         challenger_bet.side = BetSide::Challenger;
-        challenger_bet.has_voted = true; // Auto-vote for self
         challenger_bet.claimed = false;
         challenger_bet.placed_at = Clock::get()?.unix_timestamp;
         challenger_bet.bump = *ctx.bumps.get("challenger_bet").unwrap();
@@ -89,30 +650,65 @@ pub mod arena {
         let battle = &mut ctx.accounts.battle;
         
         battle.id = arena_mut.battle_count;
+        battle.payer = ctx.accounts.challenger_wallet.key();
         battle.challenger = ctx.accounts.challenger.key();
-        battle.opponent = ctx.accounts.opponent.key();
+        battle.opponent = opponent_key;
+        battle.open_terms = open_terms;
         battle.topic = topic;
+        battle.mint = mint;
         battle.status = BattleStatus::Challenge;
         battle.challenger_stake = stake;
         battle.opponent_stake = 0;
         battle.pool_challenger = stake;
         battle.pool_opponent = 0;
-        battle.votes_challenger = stake; // Auto-vote
+        battle.votes_challenger = 0;
         battle.votes_opponent = 0;
         battle.total_bets = 1;
+        battle.prize_pool = 0;
+        battle.house_fee = 0;
+        battle.winning_pool = 0;
+        battle.paid_out = 0;
+        battle.bets_closed = 0;
+        battle.votes_closed = 0;
+        battle.escrow_closed = false;
+        battle.glicko_updated = false;
         battle.voting_period = voting_period;
+        battle.reveal_period = arena_mut.reveal_period;
+        battle.claim_period = arena_mut.claim_period;
+        battle.bet_cutoff = arena_mut.bet_cutoff;
         battle.created_at = Clock::get()?.unix_timestamp;
+        battle.acceptance_deadline = battle.created_at + arena_mut.acceptance_period;
         battle.accepted_at = None;
         battle.voting_ends_at = None;
+        battle.reveal_ends_at = None;
+        battle.betting_ends_at = None;
         battle.settled_at = None;
+        battle.claim_ends_at = None;
+        battle.claim_pause_offset = 0;
         battle.winner = None;
         battle.bump = *ctx.bumps.get("battle").unwrap();
         
         arena_mut.battle_count += 1;
+        ctx.accounts.challenger.active_battles += 1;
+        record_stats(arena_mut, &mut ctx.accounts.mint_config, battle.mint, stake, 0, 0, 0)?;
+        
+        emit!(ChallengeIssued {
+            battle_id: battle.id,
+            battle: battle.key(),
+            challenger: battle.challenger,
+            opponent: ctx.accounts.opponent.as_ref().map(|opponent| opponent.key()),
+            mint: battle.mint,
+            stake,
+            acceptance_deadline: battle.acceptance_deadline,
+            timestamp: battle.created_at,
+        });
         
-        msg!("Challenge issued: {} vs {}", 
-            ctx.accounts.challenger.name,
-            ctx.accounts.opponent.name);
+        match &ctx.accounts.opponent {
+            Some(opponent) => msg!("Challenge issued: {} vs {}",
+                ctx.accounts.challenger.name,
+                opponent.name),
+            None => msg!("Open challenge issued by {}", ctx.accounts.challenger.name),
+        }
         Ok(())
     }
 
@@ -120,44 +716,99 @@ pub mod arena {
     pub fn accept_challenge(ctx: Context<AcceptChallenge>, stake: u64) -> Result<()> {
         let battle = &mut ctx.accounts.battle;
         
+        require!(!ctx.accounts.arena.is_paused(PAUSE_ACCEPT), ArenaError::Paused);
         require!(battle.status == BattleStatus::Challenge, ArenaError::NotChallenge);
-        // FIXED: Verify this is actually the challenged opponent
-        require!(ctx.accounts.opponent.key() == battle.opponent, ArenaError::NotOpponent);
-        require!(ctx.accounts.opponent_wallet.key() == ctx.accounts.opponent.wallet, ArenaError::NotOpponent);
-        require!(stake >= battle.challenger_stake, ArenaError::StakeMustMatch);
         
-        // Transfer stake to escrow
-        anchor_lang::system_program::transfer(
-            CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.opponent_wallet.to_account_info(),
-                    to: ctx.accounts.escrow.to_account_info(),
-                },
+        if let Some(terms) = &battle.open_terms {
+            // Open call: first eligible fighter to accept becomes the opponent
+            let opponent = &ctx.accounts.opponent;
+            require!(opponent.key() != battle.challenger, ArenaError::SameFighter);
+            require!(
+                opponent.elo >= terms.min_elo && opponent.elo <= terms.max_elo,
+                ArenaError::NotEligible
+            );
+            require!(stake >= terms.min_stake, ArenaError::StakeTooLow);
+            battle.opponent = opponent.key();
+        } else {
+            // FIXED: Verify this is actually the challenged opponent
+            require!(ctx.accounts.opponent.key() == battle.opponent, ArenaError::NotOpponent);
+        }
+        require!(
+            Clock::get()?.unix_timestamp < battle.acceptance_deadline,
+            ArenaError::ChallengeExpired
+        );
+        
+        let vault_spend = if battle.mint.is_none() { stake } else { 0 };
+        let is_delegate = authorize_fighter(
+            &mut ctx.accounts.opponent,
+            ctx.accounts.opponent_wallet.key(),
+            DELEGATE_ACCEPT,
+            vault_spend,
+            Clock::get()?.unix_timestamp,
+        )?;
+        
+        let opponent_key = ctx.accounts.opponent.key();
+        let vault_seeds = &[
+            b"vault".as_ref(),
+            opponent_key.as_ref(),
+            &[ctx.bumps.get("opponent_vault").copied().unwrap_or_default()],
+        ];
+        let (depositor, depositor_seeds) = stake_source(
+            is_delegate && battle.mint.is_none(),
+            &ctx.accounts.opponent_wallet,
+            &ctx.accounts.opponent_vault,
+            vault_seeds,
+        )?;
+        
+        // Transfer stake to escrow (net of any token transfer fee)
+        let stake = deposit_to_escrow(
+            battle.mint,
+            depositor,
+            depositor_seeds,
+            ctx.accounts.escrow.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            TokenLeg::from_accounts(
+                &ctx.accounts.mint,
+                &ctx.accounts.opponent_token,
+                &ctx.accounts.escrow_token,
+                &ctx.accounts.token_program,
             ),
             stake,
         )?;
+        // Compare what actually landed in escrow, since the challenger's stake is net of fees too
+        require!(stake >= battle.challenger_stake, ArenaError::StakeMustMatch);
         
         // Create opponent's bet account
         let opponent_bet = &mut ctx.accounts.opponent_bet;
         opponent_bet.battle = battle.key();
-        opponent_bet.bettor = ctx.accounts.opponent_wallet.key();
+        opponent_bet.bettor = ctx.accounts.opponent.wallet;
+        opponent_bet.payer = ctx.accounts.opponent_wallet.key();
         opponent_bet.amount = stake;
         opponent_bet.side = BetSide::Opponent;
-        opponent_bet.has_voted = true; // Auto-vote for self
         opponent_bet.claimed = false;
         opponent_bet.placed_at = Clock::get()?.unix_timestamp;
         opponent_bet.bump = *ctx.bumps.get("opponent_bet").unwrap();
         
         let now = Clock::get()?.unix_timestamp;
         
-        battle.opponent_stake = stake;
-        battle.pool_opponent = stake;
-        battle.votes_opponent = stake; // Auto-vote
-        battle.total_bets += 1;
+        add_opponent_stake(battle, stake);
+        record_stats(&mut ctx.accounts.arena, &mut ctx.accounts.mint_config, battle.mint, stake, 0, 0, 0)?;
         battle.status = BattleStatus::Live;
+        ctx.accounts.opponent.active_battles += 1;
         battle.accepted_at = Some(now);
         battle.voting_ends_at = Some(now + battle.voting_period);
+        battle.reveal_ends_at = Some(now + battle.voting_period + battle.reveal_period);
+        battle.betting_ends_at = Some(now + battle.voting_period - battle.bet_cutoff);
+        
+        emit!(ChallengeAccepted {
+            battle_id: battle.id,
+            battle: battle.key(),
+            opponent: battle.opponent,
+            stake,
+            voting_ends_at: now + battle.voting_period,
+            betting_ends_at: battle.betting_ends_at.unwrap_or_default(),
+            timestamp: now,
+        });
         
         msg!("Challenge accepted! Battle #{} is LIVE.", battle.id);
         Ok(())
@@ -170,9 +821,25 @@ pub mod arena {
         require!(battle.status == BattleStatus::Challenge, ArenaError::NotChallenge);
         // FIXED: Verify this is the actual challenger
         require!(ctx.accounts.challenger.key() == battle.challenger, ArenaError::NotChallenger);
-        require!(ctx.accounts.challenger_wallet.key() == ctx.accounts.challenger.wallet, ArenaError::NotChallenger);
+        let is_delegate = authorize_fighter(
+            &mut ctx.accounts.challenger,
+            ctx.accounts.challenger_wallet.key(),
+            DELEGATE_CANCEL,
+            0,
+            Clock::get()?.unix_timestamp,
+        )?;
+        
+        // Refund challenger stake (to the vault when a delegate cancels, never to the delegate)
+        let recipient = if is_delegate && battle.mint.is_none() {
+            ctx.accounts
+                .challenger_vault
+                .as_ref()
+                .ok_or(ArenaError::MissingVault)?
+                .to_account_info()
+        } else {
+            ctx.accounts.challenger_wallet.to_account_info()
+        };
         
-        // Refund challenger stake
         let battle_key = battle.key();
         let escrow_seeds = &[
             b"escrow".as_ref(),
@@ -180,46 +847,165 @@ pub mod arena {
             &[*ctx.bumps.get("escrow").unwrap()],
         ];
         
-        anchor_lang::system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.escrow.to_account_info(),
-                    to: ctx.accounts.challenger_wallet.to_account_info(),
-                },
-                &[escrow_seeds],
+        payout_from_escrow(
+            battle.mint,
+            ctx.accounts.escrow.to_account_info(),
+            escrow_seeds,
+            recipient,
+            ctx.accounts.system_program.to_account_info(),
+            TokenLeg::from_accounts(
+                &ctx.accounts.mint,
+                &ctx.accounts.challenger_token,
+                &ctx.accounts.escrow_token,
+                &ctx.accounts.token_program,
             ),
             battle.challenger_stake,
         )?;
         
+        // Stake is already returned, so the challenger's bet cannot be refunded again
+        ctx.accounts.challenger_bet.claimed = true;
         battle.status = BattleStatus::Cancelled;
+        ctx.accounts.challenger.active_battles -= 1;
+        let now = Clock::get()?.unix_timestamp;
+        open_claim_window(&ctx.accounts.arena, battle, now);
+        
+        emit!(ChallengeCancelled {
+            battle_id: battle.id,
+            battle: battle.key(),
+            challenger: battle.challenger,
+            refunded_stake: battle.challenger_stake,
+            timestamp: now,
+        });
         
         msg!("Challenge #{} cancelled", battle.id);
         Ok(())
     }
 
-    /// Place a bet on a fighter (AUTONOMOUS - anyone can bet)
-    pub fn place_bet(ctx: Context<PlaceBet>, amount: u64, side: BetSide) -> Result<()> {
-        let arena = &ctx.accounts.arena;
+    /// Decline a challenge (AUTONOMOUS - challenged opponent refuses, everyone is refunded)
+    pub fn decline_challenge(ctx: Context<DeclineChallenge>) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        
+        require!(battle.status == BattleStatus::Challenge, ArenaError::NotChallenge);
+        require!(ctx.accounts.opponent.key() == battle.opponent, ArenaError::NotOpponent);
+        require!(ctx.accounts.opponent_wallet.key() == ctx.accounts.opponent.wallet, ArenaError::NotOpponent);
+        
+        // Challenger stake and early bets are returned through claim_refund
+        battle.status = BattleStatus::Declined;
+        ctx.accounts.challenger.active_battles -= 1;
+        let now = Clock::get()?.unix_timestamp;
+        open_claim_window(&ctx.accounts.arena, battle, now);
+        
+        emit!(ChallengeDeclined {
+            battle_id: battle.id,
+            battle: battle.key(),
+            opponent: battle.opponent,
+            timestamp: now,
+        });
+        
+        msg!("Challenge #{} declined by {}", battle.id, ctx.accounts.opponent.name);
+        Ok(())
+    }
+
+    /// Expire a stale challenge (AUTONOMOUS - anyone can call after the acceptance deadline)
+    pub fn expire_challenge(ctx: Context<ExpireChallenge>) -> Result<()> {
         let battle = &mut ctx.accounts.battle;
         
-        require!(amount >= arena.min_bet, ArenaError::BetTooSmall);
+        require!(battle.status == BattleStatus::Challenge, ArenaError::NotChallenge);
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= battle.acceptance_deadline, ArenaError::ChallengeNotExpired);
+        
+        // Challenger stake and early bets are returned through claim_refund
+        battle.status = BattleStatus::Expired;
+        ctx.accounts.challenger.active_battles -= 1;
+        open_claim_window(&ctx.accounts.arena, battle, now);
+        
+        emit!(ChallengeExpired {
+            battle_id: battle.id,
+            battle: battle.key(),
+            timestamp: now,
+        });
+        
+        msg!("Challenge #{} expired", battle.id);
+        Ok(())
+    }
+
+    /// Claim a refund (AUTONOMOUS - bettors recover their full bet from a battle that never started
+    /// or was voided)
+    pub fn claim_refund(ctx: Context<ClaimRefund>) -> Result<()> {
+        let battle = &ctx.accounts.battle;
+        let bet = &mut ctx.accounts.bet;
+        
         require!(
-            battle.status == BattleStatus::Challenge || battle.status == BattleStatus::Live,
-            ArenaError::BattleNotOpen
+            matches!(
+                battle.status,
+                BattleStatus::Cancelled
+                    | BattleStatus::Declined
+                    | BattleStatus::Expired
+                    | BattleStatus::Void
+            ),
+            ArenaError::BattleNotRefundable
         );
-        
-        if let Some(voting_ends) = battle.voting_ends_at {
-            require!(Clock::get()?.unix_timestamp < voting_ends, ArenaError::VotingEnded);
+        require!(!bet.claimed, ArenaError::AlreadyClaimed);
+        let now = Clock::get()?.unix_timestamp;
+        if let Some(deadline) = claim_deadline(&ctx.accounts.arena, battle, now) {
+            require!(now < deadline, ArenaError::ClaimWindowClosed);
         }
         
-        anchor_lang::system_program::transfer(
-            CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.bettor.to_account_info(),
-                    to: ctx.accounts.escrow.to_account_info(),
-                },
+        let battle_key = battle.key();
+        let escrow_seeds = &[
+            b"escrow".as_ref(),
+            battle_key.as_ref(),
+            &[*ctx.bumps.get("escrow").unwrap()],
+        ];
+        
+        payout_from_escrow(
+            battle.mint,
+            ctx.accounts.escrow.to_account_info(),
+            escrow_seeds,
+            ctx.accounts.bettor.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            TokenLeg::from_accounts(
+                &ctx.accounts.mint,
+                &ctx.accounts.bettor_token,
+                &ctx.accounts.escrow_token,
+                &ctx.accounts.token_program,
+            ),
+            bet.amount,
+        )?;
+        
+        bet.claimed = true;
+        
+        emit!(RefundClaimed {
+            battle_id: battle.id,
+            battle: battle.key(),
+            bettor: bet.bettor,
+            amount: bet.amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Refunded {} lamports", bet.amount);
+        Ok(())
+    }
+
+    /// Place a bet on a fighter (AUTONOMOUS - anyone can bet)
+    pub fn place_bet(ctx: Context<PlaceBet>, amount: u64, side: BetSide) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let battle = &mut ctx.accounts.battle;
+        
+        check_bet_open(arena, battle, &ctx.accounts.mint_config, amount)?;
+        
+        // Pools are credited with what escrow actually received
+        let amount = deposit_to_escrow(
+            battle.mint,
+            ctx.accounts.bettor.to_account_info(),
+            &[],
+            ctx.accounts.escrow.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            TokenLeg::from_accounts(
+                &ctx.accounts.mint,
+                &ctx.accounts.bettor_token,
+                &ctx.accounts.escrow_token,
+                &ctx.accounts.token_program,
             ),
             amount,
         )?;
@@ -227,9 +1013,9 @@ pub mod arena {
         let bet = &mut ctx.accounts.bet;
         bet.battle = battle.key();
         bet.bettor = ctx.accounts.bettor.key();
+        bet.payer = ctx.accounts.bettor.key();
         bet.amount = amount;
         bet.side = side.clone();
-        bet.has_voted = false;
         bet.claimed = false;
         bet.placed_at = Clock::get()?.unix_timestamp;
         bet.bump = *ctx.bumps.get("bet").unwrap();
@@ -239,69 +1025,332 @@ pub mod arena {
             BetSide::Opponent => battle.pool_opponent += amount,
         }
         battle.total_bets += 1;
+        record_stats(arena, &mut ctx.accounts.mint_config, battle.mint, amount, 0, 0, 0)?;
+        
+        emit!(BetPlaced {
+            battle_id: battle.id,
+            battle: battle.key(),
+            bettor: bet.bettor,
+            side: side.clone(),
+            amount,
+            pool_challenger: battle.pool_challenger,
+            pool_opponent: battle.pool_opponent,
+            timestamp: bet.placed_at,
+        });
         
         msg!("Bet placed: {} lamports on {:?}", amount, side);
         Ok(())
     }
 
-    /// Vote on the winner (AUTONOMOUS - bettors vote, weighted by stake)
-    /// NOTE: You can only vote for the side you bet on (skin in the game)
-    pub fn vote(ctx: Context<Vote>) -> Result<()> {
+    /// Add to an existing bet on the same side (AUTONOMOUS - scale a position as the debate goes)
+    /// Entry time becomes the amount-weighted average of the old and new stakes.
+    /// NOTE: Stake bets are fixed; cancel_challenge only ever refunds the original stake.
+    pub fn increase_bet(ctx: Context<IncreaseBet>, amount: u64) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
         let battle = &mut ctx.accounts.battle;
-        let bet = &mut ctx.accounts.bet;
-        
-        require!(battle.status == BattleStatus::Live, ArenaError::BattleNotLive);
-        require!(!bet.has_voted, ArenaError::AlreadyVoted);
+        
+        check_bet_open(arena, battle, &ctx.accounts.mint_config, amount)?;
+        let opponent_wallet = match &ctx.accounts.opponent {
+            Some(opponent) => Some(opponent.wallet),
+            None => {
+                require!(battle.opponent == Pubkey::default(), ArenaError::NotOpponent);
+                None
+            }
+        };
+        require!(
+            !is_stake_bet(&ctx.accounts.bet, ctx.accounts.challenger.wallet, opponent_wallet),
+            ArenaError::StakeBetFixed
+        );
+        
+        let amount = deposit_to_escrow(
+            battle.mint,
+            ctx.accounts.bettor.to_account_info(),
+            &[],
+            ctx.accounts.escrow.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            TokenLeg::from_accounts(
+                &ctx.accounts.mint,
+                &ctx.accounts.bettor_token,
+                &ctx.accounts.escrow_token,
+                &ctx.accounts.token_program,
+            ),
+            amount,
+        )?;
+        
+        let now = Clock::get()?.unix_timestamp;
+        let bet = &mut ctx.accounts.bet;
+        let total = bet.amount + amount;
+        bet.placed_at = if total == 0 {
+            now
+        } else {
+            ((bet.placed_at as i128 * bet.amount as i128 + now as i128 * amount as i128)
+                / total as i128) as i64
+        };
+        bet.amount = total;
+        
+        match bet.side {
+            BetSide::Challenger => battle.pool_challenger += amount,
+            BetSide::Opponent => battle.pool_opponent += amount,
+        }
+        record_stats(arena, &mut ctx.accounts.mint_config, battle.mint, amount, 0, 0, 0)?;
+        
+        emit!(BetIncreased {
+            battle_id: battle.id,
+            battle: battle.key(),
+            bettor: bet.bettor,
+            side: bet.side.clone(),
+            amount,
+            total,
+            placed_at: bet.placed_at,
+            pool_challenger: battle.pool_challenger,
+            pool_opponent: battle.pool_opponent,
+            timestamp: now,
+        });
+        
+        msg!("Bet increased by {} to {} on {:?}", amount, total, bet.side);
+        Ok(())
+    }
+
+    /// Withdraw a bet before the challenge is accepted (AUTONOMOUS - bettors aren't locked into
+    /// a battle that may never start). Returns the full amount and closes the bet account.
+    /// NOTE: Like other refund paths, this is never paused.
+    pub fn withdraw_bet(ctx: Context<WithdrawBet>) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        let bet = &ctx.accounts.bet;
+        
+        require!(battle.status == BattleStatus::Challenge, ArenaError::BetLocked);
+        require!(
+            !is_stake_bet(bet, ctx.accounts.challenger.wallet, None),
+            ArenaError::StakeNotWithdrawable
+        );
+        
+        let battle_key = battle.key();
+        let escrow_seeds = &[
+            b"escrow".as_ref(),
+            battle_key.as_ref(),
+            &[*ctx.bumps.get("escrow").unwrap()],
+        ];
+        
+        payout_from_escrow(
+            battle.mint,
+            ctx.accounts.escrow.to_account_info(),
+            escrow_seeds,
+            ctx.accounts.bettor.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            TokenLeg::from_accounts(
+                &ctx.accounts.mint,
+                &ctx.accounts.bettor_token,
+                &ctx.accounts.escrow_token,
+                &ctx.accounts.token_program,
+            ),
+            bet.amount,
+        )?;
+        
+        match bet.side {
+            BetSide::Challenger => battle.pool_challenger -= bet.amount,
+            BetSide::Opponent => battle.pool_opponent -= bet.amount,
+        }
+        battle.total_bets -= 1;
+        reverse_volume(&mut ctx.accounts.arena, &mut ctx.accounts.mint_config, battle.mint, bet.amount)?;
+        
+        emit!(BetWithdrawn {
+            battle_id: battle.id,
+            battle: battle.key(),
+            bettor: bet.bettor,
+            side: bet.side.clone(),
+            amount: bet.amount,
+            pool_challenger: battle.pool_challenger,
+            pool_opponent: battle.pool_opponent,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Bet withdrawn: {} lamports", bet.amount);
+        Ok(())
+    }
+
+    /// Commit a hidden vote (AUTONOMOUS - registered fighters judge who argued better)
+    /// Commitment is sha256(choice byte || salt || voter fighter key); Challenger = 0, Opponent = 1.
+    /// NOTE: One vote per fighter, independent of any bet. Participants cannot vote.
+    pub fn commit_vote(ctx: Context<CommitVote>, commitment: [u8; 32]) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        let voter = &ctx.accounts.voter;
+        
+        require!(!ctx.accounts.arena.is_paused(PAUSE_VOTE), ArenaError::Paused);
+        require!(battle.status == BattleStatus::Live, ArenaError::BattleNotLive);
+        require!(
+            voter.key() != battle.challenger && voter.key() != battle.opponent,
+            ArenaError::ParticipantCannotVote
+        );
+        require!(ctx.accounts.arena.is_eligible_judge(voter), ArenaError::NotEligibleJudge);
         
         let now = Clock::get()?.unix_timestamp;
         if let Some(voting_ends) = battle.voting_ends_at {
             require!(now < voting_ends, ArenaError::VotingEnded);
         }
         
-        // FIXED: Vote for the side you bet on (no manipulation)
-        let weight = bet.amount;
-        match bet.side {
-            BetSide::Challenger => battle.votes_challenger += weight,
-            BetSide::Opponent => battle.votes_opponent += weight,
+        let vote_record = &mut ctx.accounts.vote_record;
+        vote_record.battle = battle.key();
+        vote_record.voter = voter.key();
+        vote_record.payer = ctx.accounts.voter_wallet.key();
+        vote_record.commitment = commitment;
+        vote_record.choice = None;
+        vote_record.committed_at = now;
+        vote_record.revealed_at = None;
+        vote_record.bump = *ctx.bumps.get("vote_record").unwrap();
+        
+        battle.votes_committed += 1;
+        
+        emit!(VoteCommitted {
+            battle_id: battle.id,
+            battle: battle.key(),
+            voter: voter.key(),
+            timestamp: now,
+        });
+        
+        msg!("Vote committed by {}", voter.name);
+        Ok(())
+    }
+
+    /// Reveal a committed vote (AUTONOMOUS - between voting end and reveal end)
+    /// NOTE: Commits that are never revealed are simply not counted.
+    pub fn reveal_vote(ctx: Context<RevealVote>, choice: BetSide, salt: [u8; 32]) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        let vote_record = &mut ctx.accounts.vote_record;
+        
+        require!(!ctx.accounts.arena.is_paused(PAUSE_VOTE), ArenaError::Paused);
+        require!(battle.status == BattleStatus::Live, ArenaError::BattleNotLive);
+        require!(vote_record.choice.is_none(), ArenaError::AlreadyRevealed);
+        
+        let now = Clock::get()?.unix_timestamp;
+        if let Some(voting_ends) = battle.voting_ends_at {
+            require!(now >= voting_ends, ArenaError::VotingNotEnded);
+        }
+        if let Some(reveal_ends) = battle.reveal_ends_at {
+            require!(now < reveal_ends, ArenaError::RevealEnded);
+        }
+        
+        let expected = vote_commitment(&choice, &salt, &vote_record.voter);
+        require!(expected == vote_record.commitment, ArenaError::CommitmentMismatch);
+        
+        match choice {
+            BetSide::Challenger => battle.votes_challenger += 1,
+            BetSide::Opponent => battle.votes_opponent += 1,
         }
         
-        bet.has_voted = true;
+        vote_record.choice = Some(choice.clone());
+        vote_record.revealed_at = Some(now);
+        
+        emit!(VoteCast {
+            battle_id: battle.id,
+            battle: battle.key(),
+            voter: vote_record.voter,
+            choice: choice.clone(),
+            votes_challenger: battle.votes_challenger,
+            votes_opponent: battle.votes_opponent,
+            timestamp: now,
+        });
+        
+        msg!("Vote revealed: {:?}", choice);
+        Ok(())
+    }
+
+    /// Record a committed vote that was never revealed against its voter (AUTONOMOUS - anyone
+    /// can crank after the reveal period). Judges with too many misses lose eligibility; the
+    /// vote record's rent goes to the caller.
+    pub fn flag_unrevealed(ctx: Context<FlagUnrevealed>) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        let voter = &mut ctx.accounts.voter;
+        
+        require!(ctx.accounts.vote_record.choice.is_none(), ArenaError::AlreadyRevealed);
+        let now = Clock::get()?.unix_timestamp;
+        let reveal_ends = battle.reveal_ends_at.ok_or(ArenaError::RevealNotEnded)?;
+        require!(now >= reveal_ends, ArenaError::RevealNotEnded);
+        
+        voter.missed_reveals += 1;
+        battle.votes_closed += 1;
+        
+        emit!(VoteUnrevealed {
+            battle_id: battle.id,
+            battle: battle.key(),
+            voter: voter.key(),
+            missed_reveals: voter.missed_reveals,
+            timestamp: now,
+        });
         
-        msg!("Vote cast: {:?} with weight {}", bet.side, weight);
+        msg!("Unrevealed vote flagged for {}", voter.name);
+        Ok(())
+    }
+
+    /// Close a vote record once the battle is over, returning rent to its payer (AUTONOMOUS -
+    /// anyone can crank). Unrevealed votes go through flag_unrevealed instead, unless the
+    /// voter's fighter account is gone and there is no one left to penalize.
+    pub fn close_vote(ctx: Context<CloseVote>) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        let vote_record = &ctx.accounts.vote_record;
+        
+        require!(battle.status.is_final(), ArenaError::BattleNotFinal);
+        require!(
+            vote_record.choice.is_some() || ctx.accounts.voter.data_is_empty(),
+            ArenaError::VoteNotRevealed
+        );
+        
+        battle.votes_closed += 1;
+        
+        emit!(VoteClosed {
+            battle_id: battle.id,
+            battle: battle.key(),
+            voter: vote_record.voter,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Vote record closed, rent returned to {}", vote_record.payer);
         Ok(())
     }
 
     /// Settle the battle (AUTONOMOUS - anyone can call after voting ends)
     pub fn settle_battle(ctx: Context<SettleBattle>) -> Result<()> {
-        let arena = &ctx.accounts.arena;
+        let arena = &mut ctx.accounts.arena;
         let battle = &mut ctx.accounts.battle;
         let challenger = &mut ctx.accounts.challenger;
         let opponent = &mut ctx.accounts.opponent;
         
+        require!(!arena.is_paused(PAUSE_SETTLE), ArenaError::Paused);
         require!(battle.status == BattleStatus::Live, ArenaError::BattleNotLive);
         
         let now = Clock::get()?.unix_timestamp;
-        if let Some(voting_ends) = battle.voting_ends_at {
-            require!(now >= voting_ends, ArenaError::VotingNotEnded);
+        if let Some(reveal_ends) = battle.reveal_ends_at {
+            require!(now >= reveal_ends, ArenaError::RevealNotEnded);
         }
         
-        // Determine winner by votes
-        let winner = if battle.votes_challenger > battle.votes_opponent {
-            BetSide::Challenger
-        } else if battle.votes_opponent > battle.votes_challenger {
-            BetSide::Opponent
-        } else {
-            // Tie: larger pool wins
-            if battle.pool_challenger >= battle.pool_opponent {
-                BetSide::Challenger
-            } else {
-                BetSide::Opponent
-            }
-        };
+        // One-sided market (e.g. a stake netted to zero by a transfer fee): there is no
+        // counterparty, so void the battle and let everyone claim a refund without a fee
+        if battle.pool_challenger == 0 || battle.pool_opponent == 0 {
+            battle.status = BattleStatus::Void;
+            battle.settled_at = Some(now);
+            open_claim_window(arena, battle, now);
+            challenger.active_battles -= 1;
+            opponent.active_battles -= 1;
+            
+            emit!(BattleVoided {
+                battle_id: battle.id,
+                battle: battle.key(),
+                pool_challenger: battle.pool_challenger,
+                pool_opponent: battle.pool_opponent,
+                timestamp: now,
+            });
+            
+            msg!("Battle #{} voided: one side has no pool", battle.id);
+            return Ok(());
+        }
         
-        // Calculate and transfer house fee to treasury
+        // Determine winner by revealed votes (tie or missed reveal quorum is a draw)
+        let winner = tally_outcome(battle.votes_challenger, battle.votes_opponent, battle.votes_committed);
+        
+        // Snapshot the payout terms; claims draw only from these numbers
         let total_pool = battle.pool_challenger + battle.pool_opponent;
-        let house_fee = (total_pool * arena.house_fee_bps as u64) / 10000;
+        let house_fee = snapshot_settlement(battle, &winner, arena.house_fee_bps);
+        
+        // Transfer house fee to treasury
         
         if house_fee > 0 {
             let battle_key = battle.key();
@@ -311,62 +1360,139 @@ pub mod arena {
                 &[*ctx.bumps.get("escrow").unwrap()],
             ];
             
-            anchor_lang::system_program::transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.system_program.to_account_info(),
-                    anchor_lang::system_program::Transfer {
-                        from: ctx.accounts.escrow.to_account_info(),
-                        to: ctx.accounts.treasury.to_account_info(),
-                    },
-                    &[escrow_seeds],
+            payout_from_escrow(
+                battle.mint,
+                ctx.accounts.escrow.to_account_info(),
+                escrow_seeds,
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                TokenLeg::from_accounts(
+                    &ctx.accounts.mint,
+                    &ctx.accounts.treasury_token,
+                    &ctx.accounts.escrow_token,
+                    &ctx.accounts.token_program,
                 ),
                 house_fee,
             )?;
         }
         
         // Update ELO
-        let challenger_wins = matches!(winner, BetSide::Challenger);
-        let (new_elo_c, new_elo_o) = calculate_new_elo(challenger.elo, opponent.elo, challenger_wins);
+        let (new_elo_c, new_elo_o) = calculate_new_elo(challenger.elo, opponent.elo, &winner);
         
-        if challenger_wins {
-            challenger.wins += 1;
-            opponent.losses += 1;
-        } else {
-            opponent.wins += 1;
-            challenger.losses += 1;
+        match winner {
+            BattleOutcome::Challenger => {
+                challenger.wins += 1;
+                opponent.losses += 1;
+            }
+            BattleOutcome::Opponent => {
+                opponent.wins += 1;
+                challenger.losses += 1;
+            }
+            BattleOutcome::Draw => {
+                challenger.draws += 1;
+                opponent.draws += 1;
+            }
         }
         
         challenger.elo = new_elo_c;
         opponent.elo = new_elo_o;
         
-        battle.winner = Some(winner.clone());
+        // Glicko-2 runs in update_glicko, so its float math never holds up payouts.
+        // Fighters stay active (and can't close) until that crank has rated them.
         battle.status = BattleStatus::Settled;
         battle.settled_at = Some(now);
+        open_claim_window(arena, battle, now);
+        
+        // Fighter earnings are tracked in lamports, so only native SOL battles count
+        if battle.mint.is_none() {
+            let payout_c = payout_share(battle, &BetSide::Challenger, battle.challenger_stake).unwrap_or(0);
+            let payout_o = payout_share(battle, &BetSide::Opponent, battle.opponent_stake).unwrap_or(0);
+            challenger.total_earnings += payout_c.saturating_sub(battle.challenger_stake);
+            challenger.total_losses += battle.challenger_stake.saturating_sub(payout_c);
+            opponent.total_earnings += payout_o.saturating_sub(battle.opponent_stake);
+            opponent.total_losses += battle.opponent_stake.saturating_sub(payout_o);
+        }
+        
+        arena.battles_settled += 1;
+        record_stats(arena, &mut ctx.accounts.mint_config, battle.mint, 0, house_fee, 0, 0)?;
+        
+        emit!(BattleSettled {
+            battle_id: battle.id,
+            battle: battle.key(),
+            winner: winner.clone(),
+            votes_challenger: battle.votes_challenger,
+            votes_opponent: battle.votes_opponent,
+            total_pool,
+            house_fee,
+            challenger_elo: new_elo_c,
+            opponent_elo: new_elo_o,
+            timestamp: now,
+        });
         
         msg!("Battle #{} settled! Winner: {:?}", battle.id, winner);
         Ok(())
     }
 
-    /// Claim winnings (AUTONOMOUS - winners claim their share)
-    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
-        let arena = &ctx.accounts.arena;
-        let battle = &ctx.accounts.battle;
-        let bet = &mut ctx.accounts.bet;
+    /// Apply the Glicko-2 update for a settled battle (AUTONOMOUS - anyone can crank)
+    /// Kept out of settle_battle so settlement cost doesn't depend on the float iterations.
+    /// Deviation first grows for the time each fighter sat idle before the settlement.
+    pub fn update_glicko(ctx: Context<UpdateGlicko>) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        let challenger = &mut ctx.accounts.challenger;
+        let opponent = &mut ctx.accounts.opponent;
         
         require!(battle.status == BattleStatus::Settled, ArenaError::BattleNotSettled);
-        require!(!bet.claimed, ArenaError::AlreadyClaimed);
-        require!(battle.winner.as_ref() == Some(&bet.side), ArenaError::NotWinner);
+        require!(!battle.glicko_updated, ArenaError::GlickoAlreadyUpdated);
+        let settled_at = battle.settled_at.ok_or(ArenaError::BattleNotSettled)?;
         
-        let (winning_pool, losing_pool) = match bet.side {
-            BetSide::Challenger => (battle.pool_challenger, battle.pool_opponent),
-            BetSide::Opponent => (battle.pool_opponent, battle.pool_challenger),
+        let glicko_c = glicko::decay(challenger.glicko(), (settled_at - challenger.last_played) / glicko::RATING_PERIOD);
+        let glicko_o = glicko::decay(opponent.glicko(), (settled_at - opponent.last_played) / glicko::RATING_PERIOD);
+        let score_c = match battle.winner {
+            Some(BattleOutcome::Challenger) => 1.0,
+            Some(BattleOutcome::Opponent) => 0.0,
+            _ => 0.5,
         };
+        challenger.set_glicko(glicko::update(glicko_c, glicko_o, score_c));
+        opponent.set_glicko(glicko::update(glicko_o, glicko_c, 1.0 - score_c));
+        challenger.last_played = challenger.last_played.max(settled_at);
+        opponent.last_played = opponent.last_played.max(settled_at);
+        challenger.active_battles -= 1;
+        opponent.active_battles -= 1;
+        battle.glicko_updated = true;
+        
+        emit!(GlickoUpdated {
+            battle_id: battle.id,
+            battle: battle.key(),
+            challenger_rating: challenger.glicko_rating,
+            challenger_deviation: challenger.glicko_deviation,
+            opponent_rating: opponent.glicko_rating,
+            opponent_deviation: opponent.glicko_deviation,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Glicko-2 updated for battle #{}", battle.id);
+        Ok(())
+    }
+
+    /// Claim winnings (AUTONOMOUS - winners claim their share, everyone is refunded minus fee on a draw)
+    pub fn claim_winnings(ctx: Context<ClaimWinnings>) -> Result<()> {
+        let arena = &mut ctx.accounts.arena;
+        let battle = &mut ctx.accounts.battle;
+        let bet = &mut ctx.accounts.bet;
         
-        let total_pool = winning_pool + losing_pool;
-        let house_fee = (total_pool * arena.house_fee_bps as u64) / 10000;
-        let prize_pool = total_pool - house_fee;
+        require!(!arena.is_paused(PAUSE_CLAIM), ArenaError::Paused);
+        require!(battle.status == BattleStatus::Settled, ArenaError::BattleNotSettled);
+        require!(!bet.claimed, ArenaError::AlreadyClaimed);
+        let now = Clock::get()?.unix_timestamp;
+        if let Some(deadline) = claim_deadline(arena, battle, now) {
+            require!(now < deadline, ArenaError::ClaimWindowClosed);
+        }
         
-        let winnings = (bet.amount as u128 * prize_pool as u128 / winning_pool as u128) as u64;
+        let winnings = payout_share(battle, &bet.side, bet.amount).ok_or(ArenaError::NotWinner)?;
+        require!(
+            battle.paid_out + winnings <= battle.prize_pool,
+            ArenaError::EscrowInsolvent
+        );
         
         let battle_key = battle.key();
         let escrow_seeds = &[
@@ -375,23 +1501,144 @@ pub mod arena {
             &[*ctx.bumps.get("escrow").unwrap()],
         ];
         
-        anchor_lang::system_program::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.escrow.to_account_info(),
-                    to: ctx.accounts.bettor.to_account_info(),
-                },
-                &[escrow_seeds],
+        payout_from_escrow(
+            battle.mint,
+            ctx.accounts.escrow.to_account_info(),
+            escrow_seeds,
+            ctx.accounts.bettor.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            TokenLeg::from_accounts(
+                &ctx.accounts.mint,
+                &ctx.accounts.bettor_token,
+                &ctx.accounts.escrow_token,
+                &ctx.accounts.token_program,
             ),
             winnings,
         )?;
         
         bet.claimed = true;
+        battle.paid_out += winnings;
+        record_stats(arena, &mut ctx.accounts.mint_config, battle.mint, 0, 0, winnings, 0)?;
+        
+        emit!(WinningsClaimed {
+            battle_id: battle.id,
+            battle: battle.key(),
+            bettor: bet.bettor,
+            amount: winnings,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
         
         msg!("Claimed {} lamports", winnings);
         Ok(())
     }
+
+    /// Close a finished bet and return its rent to the payer (AUTONOMOUS - anyone can crank)
+    /// A bet is finished once claimed or refunded, once settlement makes it a loser,
+    /// or once the escrow has been swept after the claim or refund window.
+    pub fn close_bet(ctx: Context<CloseBet>) -> Result<()> {
+        let battle = &mut ctx.accounts.battle;
+        let bet = &ctx.accounts.bet;
+        
+        let finished = match battle.status {
+            BattleStatus::Settled => {
+                bet.claimed || battle.escrow_closed || !bet_wins(battle, &bet.side)
+            }
+            BattleStatus::Cancelled
+            | BattleStatus::Declined
+            | BattleStatus::Expired
+            | BattleStatus::Void => bet.claimed || battle.escrow_closed,
+            BattleStatus::Challenge | BattleStatus::Live => false,
+        };
+        require!(finished, ArenaError::BetNotFinished);
+        
+        battle.bets_closed += 1;
+        
+        emit!(BetClosed {
+            battle_id: battle.id,
+            battle: battle.key(),
+            bettor: bet.bettor,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Bet closed, rent returned to {}", bet.payer);
+        Ok(())
+    }
+
+    /// Close the escrow once every bet is closed (AUTONOMOUS - anyone can crank)
+    /// Rounding dust goes to the treasury; the token escrow's rent goes back to the battle payer.
+    pub fn close_escrow(ctx: Context<CloseEscrow>) -> Result<()> {
+        let battle = &ctx.accounts.battle;
+        
+        require!(battle.status.is_final(), ArenaError::BattleNotFinal);
+        require!(!battle.escrow_closed, ArenaError::EscrowClosed);
+        require!(battle.bets_closed == battle.total_bets, ArenaError::BetsOutstanding);
+        
+        let dust = drain_escrow(ctx.accounts, *ctx.bumps.get("escrow").unwrap())?;
+        let battle = &ctx.accounts.battle;
+        
+        emit!(EscrowClosed {
+            battle_id: battle.id,
+            battle: battle.key(),
+            dust,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Escrow closed, {} dust to treasury", dust);
+        Ok(())
+    }
+
+    /// Sweep unclaimed winnings or refunds and dust to the treasury once the claim window has
+    /// passed (AUTONOMOUS - anyone can crank). Remaining bets can then be closed.
+    /// Blocked while claims are paused; the window itself is extended by any claim pause.
+    pub fn sweep_escrow(ctx: Context<CloseEscrow>) -> Result<()> {
+        let arena = &ctx.accounts.arena;
+        let battle = &ctx.accounts.battle;
+        
+        require!(!arena.is_paused(PAUSE_CLAIM), ArenaError::Paused);
+        require!(battle.status.is_final(), ArenaError::BattleNotFinal);
+        require!(!battle.escrow_closed, ArenaError::EscrowClosed);
+        let now = Clock::get()?.unix_timestamp;
+        let deadline = claim_deadline(arena, battle, now).ok_or(ArenaError::ClaimWindowOpen)?;
+        require!(now >= deadline, ArenaError::ClaimWindowOpen);
+        
+        let amount = drain_escrow(ctx.accounts, *ctx.bumps.get("escrow").unwrap())?;
+        let battle = &ctx.accounts.battle;
+        
+        emit!(EscrowSwept {
+            battle_id: battle.id,
+            battle: battle.key(),
+            treasury: ctx.accounts.treasury.key(),
+            amount,
+            open_bets: battle.total_bets - battle.bets_closed,
+            timestamp: now,
+        });
+        
+        msg!("Swept {} to treasury", amount);
+        Ok(())
+    }
+
+    /// Close a finished battle after its escrow and bets are closed, returning rent to the payer
+    /// (AUTONOMOUS - anyone can crank; indexers keep the history from events)
+    pub fn close_battle(ctx: Context<CloseBattle>) -> Result<()> {
+        let battle = &ctx.accounts.battle;
+        
+        require!(battle.escrow_closed, ArenaError::EscrowOpen);
+        require!(battle.bets_closed == battle.total_bets, ArenaError::BetsOutstanding);
+        require!(battle.votes_closed == battle.votes_committed, ArenaError::VotesOutstanding);
+        require!(
+            battle.status != BattleStatus::Settled || battle.glicko_updated,
+            ArenaError::GlickoPending
+        );
+        
+        emit!(BattleClosed {
+            battle_id: battle.id,
+            battle: battle.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Battle #{} closed", battle.id);
+        Ok(())
+    }
 }
 
 // === ACCOUNTS ===
@@ -417,16 +1664,36 @@ pub struct Initialize<'info> {
 }
 
 #[derive(Accounts)]
-pub struct RegisterFighter<'info> {
-    #[account(
-        init,
-        payer = wallet,
-        space = 8 + Fighter::INIT_SPACE,
-        seeds = [b"fighter", wallet.key().as_ref()],
-        bump
-    )]
-    pub fighter: Account<'info, Fighter>,
+pub struct UpdateConfig<'info> {
+    #[account(mut, seeds = [b"arena"], bump = arena.bump, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+    
+    /// CHECK: Treasury for house fees
+    pub treasury: UncheckedAccount<'info>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateArena<'info> {
+    /// CHECK: Arena in the legacy layout, validated and read by hand in migrate_arena
+    #[account(mut, seeds = [b"arena"], bump)]
+    pub arena: UncheckedAccount<'info>,
+    
+    /// Pays for the extra space
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateFighter<'info> {
+    /// CHECK: Fighter in the legacy layout, validated and read by hand in migrate_fighter
+    #[account(mut, seeds = [b"fighter", wallet.key().as_ref()], bump)]
+    pub fighter: UncheckedAccount<'info>,
     
+    /// Pays for the extra space
     #[account(mut)]
     pub wallet: Signer<'info>,
     
@@ -434,57 +1701,204 @@ pub struct RegisterFighter<'info> {
 }
 
 #[derive(Accounts)]
-pub struct Challenge<'info> {
+pub struct ProposeAuthority<'info> {
+    #[account(mut, seeds = [b"arena"], bump = arena.bump, has_one = authority)]
+    pub arena: Account<'info, Arena>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
     #[account(mut, seeds = [b"arena"], bump = arena.bump)]
-    pub arena: Box<Account<'info, Arena>>,
+    pub arena: Account<'info, Arena>,
     
-    #[account(
-        init,
-        payer = challenger_wallet,
-        space = 8 + Battle::INIT_SPACE,
-        seeds = [b"battle", arena.battle_count.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub battle: Box<Account<'info, Battle>>,
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPause<'info> {
+    #[account(mut, seeds = [b"arena"], bump = arena.bump, has_one = authority)]
+    pub arena: Account<'info, Arena>,
     
-    #[account(
-        init,
-        payer = challenger_wallet,
-        space = 8 + Bet::INIT_SPACE,
-        seeds = [b"bet", battle.key().as_ref(), challenger_wallet.key().as_ref()],
-        bump
-    )]
-    pub challenger_bet: Account<'info, Bet>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureMint<'info> {
+    #[account(seeds = [b"arena"], bump = arena.bump, has_one = authority)]
+    pub arena: Account<'info, Arena>,
     
-    /// CHECK: Escrow for stakes and bets
     #[account(
-        mut,
-        seeds = [b"escrow", battle.key().as_ref()],
+        init_if_needed,
+        payer = authority,
+        space = 8 + MintConfig::INIT_SPACE,
+        seeds = [b"mint_config", mint.key().as_ref()],
         bump
     )]
-    pub escrow: UncheckedAccount<'info>,
-    
-    #[account(constraint = challenger.wallet == challenger_wallet.key())]
-    pub challenger: Box<Account<'info, Fighter>>,
+    pub mint_config: Account<'info, MintConfig>,
     
-    pub opponent: Box<Account<'info, Fighter>>,
+    pub mint: InterfaceAccount<'info, Mint>,
     
     #[account(mut)]
-    pub challenger_wallet: Signer<'info>,
+    pub authority: Signer<'info>,
     
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct AcceptChallenge<'info> {
-    #[account(mut)]
-    pub battle: Account<'info, Battle>,
+pub struct RegisterFighter<'info> {
+    #[account(
+        init,
+        payer = wallet,
+        space = 8 + Fighter::INIT_SPACE,
+        seeds = [b"fighter", wallet.key().as_ref()],
+        bump
+    )]
+    pub fighter: Account<'info, Fighter>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFighter<'info> {
+    #[account(
+        mut,
+        seeds = [b"fighter", wallet.key().as_ref()],
+        bump = fighter.bump,
+        has_one = wallet
+    )]
+    pub fighter: Account<'info, Fighter>,
+    
+    pub wallet: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseFighter<'info> {
+    #[account(
+        mut,
+        close = wallet,
+        seeds = [b"fighter", wallet.key().as_ref()],
+        bump = fighter.bump,
+        has_one = wallet
+    )]
+    pub fighter: Account<'info, Fighter>,
+    
+    /// CHECK: Fighter vault, emptied to the wallet
+    #[account(mut, seeds = [b"vault", fighter.key().as_ref()], bump)]
+    pub vault: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawVault<'info> {
+    #[account(seeds = [b"fighter", wallet.key().as_ref()], bump = fighter.bump, has_one = wallet)]
+    pub fighter: Account<'info, Fighter>,
+    
+    /// CHECK: Fighter vault
+    #[account(mut, seeds = [b"vault", fighter.key().as_ref()], bump)]
+    pub vault: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Challenge<'info> {
+    #[account(mut, seeds = [b"arena"], bump = arena.bump)]
+    pub arena: Box<Account<'info, Arena>>,
+    
+    #[account(
+        init,
+        payer = challenger_wallet,
+        space = 8 + Battle::INIT_SPACE,
+        seeds = [b"battle", arena.battle_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub battle: Box<Account<'info, Battle>>,
+    
+    #[account(
+        init,
+        payer = challenger_wallet,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [b"bet", battle.key().as_ref(), challenger.wallet.as_ref()],
+        bump
+    )]
+    pub challenger_bet: Account<'info, Bet>,
+    
+    /// CHECK: Escrow for stakes and bets
+    #[account(
+        mut,
+        seeds = [b"escrow", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub challenger: Box<Account<'info, Fighter>>,
+    
+    /// None for an open challenge
+    pub opponent: Option<Box<Account<'info, Fighter>>>,
+    
+    /// Fighter wallet, or its delegate
+    #[account(mut)]
+    pub challenger_wallet: Signer<'info>,
+    
+    /// CHECK: Fighter vault, funds delegate-signed SOL stakes
+    #[account(mut, seeds = [b"vault", challenger.key().as_ref()], bump)]
+    pub challenger_vault: Option<UncheckedAccount<'info>>,
+    
+    /// SPL mint the battle is denominated in (None = native SOL)
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    
+    #[account(mut)]
+    pub mint_config: Option<Box<Account<'info, MintConfig>>>,
+    
+    /// Token escrow owned by the escrow PDA (SPL battles only)
+    #[account(
+        init,
+        payer = challenger_wallet,
+        token::mint = mint,
+        token::authority = escrow,
+        seeds = [b"escrow_token", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    #[account(mut)]
+    pub challenger_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptChallenge<'info> {
+    #[account(mut, seeds = [b"arena"], bump = arena.bump)]
+    pub arena: Box<Account<'info, Arena>>,
+    
+    /// Required for SPL battles (per-mint stats)
+    #[account(mut, constraint = battle.mint == Some(mint_config.mint) @ ArenaError::MintNotAllowed)]
+    pub mint_config: Option<Box<Account<'info, MintConfig>>>,
+    
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
     
     #[account(
         init,
         payer = opponent_wallet,
         space = 8 + Bet::INIT_SPACE,
-        seeds = [b"bet", battle.key().as_ref(), opponent_wallet.key().as_ref()],
+        seeds = [b"bet", battle.key().as_ref(), opponent.wallet.as_ref()],
         bump
     )]
     pub opponent_bet: Account<'info, Bet>,
@@ -497,17 +1911,44 @@ pub struct AcceptChallenge<'info> {
     )]
     pub escrow: UncheckedAccount<'info>,
     
-    #[account(constraint = opponent.key() == battle.opponent @ ArenaError::NotOpponent)]
+    #[account(
+        mut,
+        constraint = battle.open_terms.is_some() || opponent.key() == battle.opponent @ ArenaError::NotOpponent
+    )]
     pub opponent: Account<'info, Fighter>,
     
+    /// Fighter wallet, or its delegate
     #[account(mut)]
     pub opponent_wallet: Signer<'info>,
     
+    /// CHECK: Fighter vault, funds delegate-signed SOL stakes
+    #[account(mut, seeds = [b"vault", opponent.key().as_ref()], bump)]
+    pub opponent_vault: Option<UncheckedAccount<'info>>,
+    
+    /// Battle's SPL mint (SPL battles only)
+    #[account(constraint = battle.mint == Some(mint.key()) @ ArenaError::MintNotAllowed)]
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow_token", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    #[account(mut)]
+    pub opponent_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct CancelChallenge<'info> {
+    #[account(seeds = [b"arena"], bump = arena.bump)]
+    pub arena: Account<'info, Arena>,
+    
     #[account(mut)]
     pub battle: Account<'info, Battle>,
     
@@ -519,28 +1960,88 @@ pub struct CancelChallenge<'info> {
     )]
     pub escrow: UncheckedAccount<'info>,
     
-    #[account(constraint = challenger.key() == battle.challenger @ ArenaError::NotChallenger)]
+    #[account(
+        mut,
+        seeds = [b"bet", battle.key().as_ref(), challenger.wallet.as_ref()],
+        bump = challenger_bet.bump
+    )]
+    pub challenger_bet: Account<'info, Bet>,
+    
+    #[account(mut, constraint = challenger.key() == battle.challenger @ ArenaError::NotChallenger)]
     pub challenger: Account<'info, Fighter>,
     
+    /// Fighter wallet, or its delegate
     #[account(mut)]
     pub challenger_wallet: Signer<'info>,
     
+    /// CHECK: Fighter vault, receives the refund when a delegate cancels
+    #[account(mut, seeds = [b"vault", challenger.key().as_ref()], bump)]
+    pub challenger_vault: Option<UncheckedAccount<'info>>,
+    
+    /// Battle's SPL mint (SPL battles only)
+    #[account(constraint = battle.mint == Some(mint.key()) @ ArenaError::MintNotAllowed)]
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow_token", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    /// Owner's token account (SPL battles only)
+    #[account(mut, constraint = challenger_token.owner == challenger.wallet @ ArenaError::NotChallenger)]
+    pub challenger_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct PlaceBet<'info> {
+pub struct DeclineChallenge<'info> {
+    #[account(seeds = [b"arena"], bump = arena.bump)]
+    pub arena: Account<'info, Arena>,
+    
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    
+    #[account(mut, constraint = challenger.key() == battle.challenger @ ArenaError::NotChallenger)]
+    pub challenger: Account<'info, Fighter>,
+    
+    #[account(constraint = opponent.key() == battle.opponent @ ArenaError::NotOpponent)]
+    pub opponent: Account<'info, Fighter>,
+    
+    pub opponent_wallet: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireChallenge<'info> {
+    #[account(seeds = [b"arena"], bump = arena.bump)]
     pub arena: Account<'info, Arena>,
     
     #[account(mut)]
     pub battle: Account<'info, Battle>,
     
+    #[account(mut, constraint = challenger.key() == battle.challenger @ ArenaError::NotChallenger)]
+    pub challenger: Account<'info, Fighter>,
+    
+    /// Anyone can call expire
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRefund<'info> {
+    #[account(seeds = [b"arena"], bump = arena.bump)]
+    pub arena: Account<'info, Arena>,
+    
+    pub battle: Account<'info, Battle>,
+    
     #[account(
-        init,
-        payer = bettor,
-        space = 8 + Bet::INIT_SPACE,
+        mut,
+        has_one = bettor,
         seeds = [b"bet", battle.key().as_ref(), bettor.key().as_ref()],
-        bump
+        bump = bet.bump
     )]
     pub bet: Account<'info, Bet>,
     
@@ -555,32 +2056,104 @@ pub struct PlaceBet<'info> {
     #[account(mut)]
     pub bettor: Signer<'info>,
     
+    /// Battle's SPL mint (SPL battles only)
+    #[account(constraint = battle.mint == Some(mint.key()) @ ArenaError::MintNotAllowed)]
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow_token", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    #[account(mut)]
+    pub bettor_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct Vote<'info> {
+pub struct PlaceBet<'info> {
+    #[account(mut, seeds = [b"arena"], bump = arena.bump)]
+    pub arena: Account<'info, Arena>,
+    
+    /// Required for SPL battles (per-mint minimum bet and stats)
+    #[account(mut)]
+    pub mint_config: Option<Box<Account<'info, MintConfig>>>,
+    
     #[account(mut)]
     pub battle: Account<'info, Battle>,
     
-    #[account(mut, has_one = bettor)]
+    #[account(
+        init,
+        payer = bettor,
+        space = 8 + Bet::INIT_SPACE,
+        seeds = [b"bet", battle.key().as_ref(), bettor.key().as_ref()],
+        bump
+    )]
     pub bet: Account<'info, Bet>,
     
+    /// CHECK: Escrow
+    #[account(
+        mut,
+        seeds = [b"escrow", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow: UncheckedAccount<'info>,
+    
+    #[account(mut)]
     pub bettor: Signer<'info>,
+    
+    /// Battle's SPL mint (SPL battles only)
+    #[account(constraint = battle.mint == Some(mint.key()) @ ArenaError::MintNotAllowed)]
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow_token", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    #[account(mut)]
+    pub bettor_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SettleBattle<'info> {
+pub struct IncreaseBet<'info> {
+    #[account(mut, seeds = [b"arena"], bump = arena.bump)]
     pub arena: Account<'info, Arena>,
     
+    /// Required for SPL battles (per-mint minimum bet and stats)
+    #[account(mut)]
+    pub mint_config: Option<Box<Account<'info, MintConfig>>>,
+    
     #[account(mut)]
     pub battle: Account<'info, Battle>,
     
-    #[account(mut, constraint = challenger.key() == battle.challenger)]
+    #[account(
+        mut,
+        has_one = bettor,
+        has_one = battle,
+        seeds = [b"bet", battle.key().as_ref(), bettor.key().as_ref()],
+        bump = bet.bump
+    )]
+    pub bet: Account<'info, Bet>,
+    
+    /// Stake bets can't be increased
+    #[account(constraint = challenger.key() == battle.challenger @ ArenaError::NotChallenger)]
     pub challenger: Account<'info, Fighter>,
     
-    #[account(mut, constraint = opponent.key() == battle.opponent)]
-    pub opponent: Account<'info, Fighter>,
+    /// Required once the battle has an opponent
+    #[account(constraint = opponent.key() == battle.opponent @ ArenaError::NotOpponent)]
+    pub opponent: Option<Account<'info, Fighter>>,
     
     /// CHECK: Escrow
     #[account(
@@ -590,23 +2163,52 @@ pub struct SettleBattle<'info> {
     )]
     pub escrow: UncheckedAccount<'info>,
     
-    /// CHECK: Treasury receives house fee
-    #[account(mut, constraint = treasury.key() == arena.treasury)]
-    pub treasury: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub bettor: Signer<'info>,
     
-    /// Anyone can call settle
-    pub settler: Signer<'info>,
+    /// Battle's SPL mint (SPL battles only)
+    #[account(constraint = battle.mint == Some(mint.key()) @ ArenaError::MintNotAllowed)]
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow_token", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    #[account(mut)]
+    pub bettor_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
     
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimWinnings<'info> {
+pub struct WithdrawBet<'info> {
+    #[account(mut, seeds = [b"arena"], bump = arena.bump)]
     pub arena: Account<'info, Arena>,
     
+    /// Required for SPL battles (per-mint stats)
+    #[account(mut)]
+    pub mint_config: Option<Box<Account<'info, MintConfig>>>,
+    
+    #[account(mut)]
     pub battle: Account<'info, Battle>,
     
-    #[account(mut, has_one = bettor)]
+    /// Its stake bet can only be returned through cancel_challenge
+    #[account(constraint = challenger.key() == battle.challenger @ ArenaError::NotChallenger)]
+    pub challenger: Account<'info, Fighter>,
+    
+    #[account(
+        mut,
+        has_one = bettor,
+        has_one = battle,
+        close = bettor,
+        seeds = [b"bet", battle.key().as_ref(), bettor.key().as_ref()],
+        bump = bet.bump
+    )]
     pub bet: Account<'info, Bet>,
     
     /// CHECK: Escrow
@@ -620,14 +2222,359 @@ pub struct ClaimWinnings<'info> {
     #[account(mut)]
     pub bettor: Signer<'info>,
     
-    pub system_program: Program<'info, System>,
+    /// Battle's SPL mint (SPL battles only)
+    #[account(constraint = battle.mint == Some(mint.key()) @ ArenaError::MintNotAllowed)]
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow_token", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    #[account(mut)]
+    pub bettor_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CommitVote<'info> {
+    #[account(seeds = [b"arena"], bump = arena.bump)]
+    pub arena: Account<'info, Arena>,
+    
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    
+    #[account(
+        init,
+        payer = voter_wallet,
+        space = 8 + VoteRecord::INIT_SPACE,
+        seeds = [b"vote", battle.key().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    
+    #[account(constraint = voter.wallet == voter_wallet.key())]
+    pub voter: Account<'info, Fighter>,
+    
+    #[account(mut)]
+    pub voter_wallet: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevealVote<'info> {
+    #[account(seeds = [b"arena"], bump = arena.bump)]
+    pub arena: Account<'info, Arena>,
+    
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    
+    #[account(
+        mut,
+        seeds = [b"vote", battle.key().as_ref(), voter.key().as_ref()],
+        bump = vote_record.bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    
+    #[account(constraint = voter.wallet == voter_wallet.key())]
+    pub voter: Account<'info, Fighter>,
+    
+    pub voter_wallet: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FlagUnrevealed<'info> {
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    
+    #[account(
+        mut,
+        has_one = battle,
+        has_one = voter,
+        close = caller,
+        seeds = [b"vote", battle.key().as_ref(), voter.key().as_ref()],
+        bump = vote_record.bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    
+    #[account(mut)]
+    pub voter: Account<'info, Fighter>,
+    
+    /// Anyone can flag; receives the vote record's rent
+    #[account(mut)]
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseVote<'info> {
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    
+    #[account(
+        mut,
+        has_one = battle,
+        has_one = voter,
+        has_one = payer,
+        close = payer,
+        seeds = [b"vote", battle.key().as_ref(), voter.key().as_ref()],
+        bump = vote_record.bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    
+    /// CHECK: Voting fighter; only read to see whether it has been closed
+    pub voter: UncheckedAccount<'info>,
+    
+    /// CHECK: Original payer of the vote record, receives its rent
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+    
+    /// Anyone can close a finished vote record
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGlicko<'info> {
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    
+    #[account(mut, constraint = challenger.key() == battle.challenger @ ArenaError::NotChallenger)]
+    pub challenger: Account<'info, Fighter>,
+    
+    #[account(mut, constraint = opponent.key() == battle.opponent @ ArenaError::NotOpponent)]
+    pub opponent: Account<'info, Fighter>,
+    
+    /// Anyone can crank the rating update
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleBattle<'info> {
+    #[account(mut, seeds = [b"arena"], bump = arena.bump)]
+    pub arena: Account<'info, Arena>,
+    
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    
+    /// Required for SPL battles (per-mint stats)
+    #[account(mut, constraint = battle.mint == Some(mint_config.mint) @ ArenaError::MintNotAllowed)]
+    pub mint_config: Option<Box<Account<'info, MintConfig>>>,
+    
+    #[account(mut, constraint = challenger.key() == battle.challenger)]
+    pub challenger: Account<'info, Fighter>,
+    
+    #[account(mut, constraint = opponent.key() == battle.opponent)]
+    pub opponent: Account<'info, Fighter>,
+    
+    /// CHECK: Escrow
+    #[account(
+        mut,
+        seeds = [b"escrow", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow: UncheckedAccount<'info>,
+    
+    /// CHECK: Treasury receives house fee
+    #[account(mut, constraint = treasury.key() == arena.treasury)]
+    pub treasury: UncheckedAccount<'info>,
+    
+    /// Battle's SPL mint (SPL battles only)
+    #[account(constraint = battle.mint == Some(mint.key()) @ ArenaError::MintNotAllowed)]
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow_token", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    /// Treasury token account receives house fee (SPL battles only)
+    #[account(mut, constraint = treasury_token.owner == arena.treasury)]
+    pub treasury_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    /// Anyone can call settle
+    pub settler: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWinnings<'info> {
+    #[account(mut, seeds = [b"arena"], bump = arena.bump)]
+    pub arena: Account<'info, Arena>,
+    
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    
+    /// Required for SPL battles (per-mint stats)
+    #[account(mut, constraint = battle.mint == Some(mint_config.mint) @ ArenaError::MintNotAllowed)]
+    pub mint_config: Option<Box<Account<'info, MintConfig>>>,
+    
+    #[account(mut, has_one = bettor, has_one = battle)]
+    pub bet: Account<'info, Bet>,
+    
+    /// CHECK: Escrow
+    #[account(
+        mut,
+        seeds = [b"escrow", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    
+    /// Battle's SPL mint (SPL battles only)
+    #[account(constraint = battle.mint == Some(mint.key()) @ ArenaError::MintNotAllowed)]
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow_token", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    #[account(mut)]
+    pub bettor_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBet<'info> {
+    #[account(mut)]
+    pub battle: Account<'info, Battle>,
+    
+    #[account(mut, has_one = battle, has_one = payer, close = payer)]
+    pub bet: Account<'info, Bet>,
+    
+    /// CHECK: Original payer of the bet account, receives its rent
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+    
+    /// Anyone can close a finished bet
+    pub caller: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseEscrow<'info> {
+    #[account(mut, seeds = [b"arena"], bump = arena.bump)]
+    pub arena: Account<'info, Arena>,
+    
+    #[account(mut, has_one = payer)]
+    pub battle: Account<'info, Battle>,
+    
+    /// Required for SPL battles (per-mint stats)
+    #[account(mut, constraint = battle.mint == Some(mint_config.mint) @ ArenaError::MintNotAllowed)]
+    pub mint_config: Option<Box<Account<'info, MintConfig>>>,
+    
+    /// CHECK: Escrow
+    #[account(
+        mut,
+        seeds = [b"escrow", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow: UncheckedAccount<'info>,
+    
+    /// CHECK: Treasury receives dust and swept winnings
+    #[account(mut, constraint = treasury.key() == arena.treasury)]
+    pub treasury: UncheckedAccount<'info>,
+    
+    /// CHECK: Battle payer, receives the token escrow's rent
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+    
+    /// Battle's SPL mint (SPL battles only; receives harvested transfer fees)
+    #[account(mut, constraint = battle.mint == Some(mint.key()) @ ArenaError::MintNotAllowed)]
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    
+    #[account(
+        mut,
+        seeds = [b"escrow_token", battle.key().as_ref()],
+        bump
+    )]
+    pub escrow_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    /// Treasury token account receives dust and swept winnings (SPL battles only)
+    #[account(mut, constraint = treasury_token.owner == arena.treasury)]
+    pub treasury_token: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    
+    /// Anyone can close the escrow
+    pub caller: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBattle<'info> {
+    #[account(mut, has_one = payer, close = payer)]
+    pub battle: Account<'info, Battle>,
+    
+    /// CHECK: Original payer of the battle account, receives its rent
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+    
+    /// Anyone can close a finished battle
+    pub caller: Signer<'info>,
 }
 
 // === STATE ===
 
 #[account]
 #[derive(InitSpace)]
+#[cfg_attr(test, derive(Default))]
 pub struct Arena {
+    pub authority: Pubkey,
+    pub pending_authority: Option<Pubkey>,
+    /// Bitmask of PAUSE_* flags
+    pub paused: u8,
+    pub treasury: Pubkey,
+    pub house_fee_bps: u16,
+    pub min_bet: u64,
+    pub min_stake_to_create: u64,
+    pub voting_period: i64,
+    pub acceptance_period: i64,
+    pub reveal_period: i64,
+    /// How long winners have to claim after settlement before the escrow can be swept
+    pub claim_period: i64,
+    /// Betting closes this many seconds before voting ends
+    pub bet_cutoff: i64,
+    /// Judge eligibility: fighters must have played and rated this well to vote,
+    /// so freshly registered (Sybil) fighters can't decide outcomes
+    pub min_judge_battles: u32,
+    pub min_judge_elo: u32,
+    pub max_missed_reveals: u32,
+    pub battle_count: u64,
+    pub battles_settled: u64,
+    /// Native SOL only (SPL volume is tracked per mint on MintConfig)
+    pub total_volume: u64,
+    pub total_fees_collected: u64,
+    pub total_paid_out: u64,
+    /// Unclaimed payouts and rounding dust swept from escrows to the treasury
+    pub total_swept: u64,
+    /// Total seconds claims have been paused, so claim deadlines can be pushed back
+    pub claim_pause_total: i64,
+    /// Set while PAUSE_CLAIM is on
+    pub claim_paused_at: Option<i64>,
+    pub bump: u8,
+}
+
+/// Arena layout as first deployed, read only by migrate_arena
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyArena {
     pub authority: Pubkey,
     pub treasury: Pubkey,
     pub house_fee_bps: u16,
@@ -639,8 +2586,29 @@ pub struct Arena {
     pub bump: u8,
 }
 
+impl Arena {
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.paused & flag != 0
+    }
+
+    /// Seconds claims have been paused as of `now`, including a pause still in effect
+    pub fn claim_pause_elapsed(&self, now: i64) -> i64 {
+        self.claim_pause_total + self.claim_paused_at.map_or(0, |paused_at| now - paused_at)
+    }
+
+    /// Whether `fighter` has enough battles and rating, and few enough missed reveals,
+    /// to judge others
+    pub fn is_eligible_judge(&self, fighter: &Fighter) -> bool {
+        let battles = fighter.wins + fighter.losses + fighter.draws;
+        battles >= self.min_judge_battles
+            && fighter.elo >= self.min_judge_elo
+            && fighter.missed_reveals <= self.max_missed_reveals
+    }
+}
+
 #[account]
 #[derive(InitSpace)]
+#[cfg_attr(test, derive(Default))]
 pub struct Fighter {
     pub wallet: Pubkey,
     #[max_len(32)]
@@ -649,19 +2617,77 @@ pub struct Fighter {
     pub wins: u32,
     pub losses: u32,
     pub draws: u32,
+    /// Net lamports won from native SOL battles
+    pub total_earnings: u64,
+    /// Lamports lost (including fees on draws) from native SOL battles
+    pub total_losses: u64,
+    pub registered_at: i64,
+    pub glicko_rating: f64,
+    pub glicko_deviation: f64,
+    pub glicko_volatility: f64,
+    pub last_played: i64,
+    /// Off-chain metadata (JSON) URI
+    #[max_len(200)]
+    pub metadata_uri: String,
+    /// Agent battle endpoint the orchestrator calls
+    #[max_len(200)]
+    pub endpoint: String,
+    /// Model/version description
+    #[max_len(64)]
+    pub model: String,
+    pub avatar_hash: [u8; 32],
+    /// Pending challenges it issued plus live battles; must be 0 to close
+    pub active_battles: u32,
+    pub delegate: Option<SessionKey>,
+    /// Votes committed but never revealed (counts against judge eligibility)
+    pub missed_reveals: u32,
+    pub bump: u8,
+}
+
+/// Fighter layout as first deployed, read only by migrate_fighter
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyFighter {
+    pub wallet: Pubkey,
+    pub name: String,
+    pub elo: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
     pub total_earnings: u64,
     pub registered_at: i64,
     pub bump: u8,
 }
 
+impl Fighter {
+    pub fn glicko(&self) -> glicko::Rating {
+        glicko::Rating {
+            rating: self.glicko_rating,
+            deviation: self.glicko_deviation,
+            volatility: self.glicko_volatility,
+        }
+    }
+
+    pub fn set_glicko(&mut self, rating: glicko::Rating) {
+        self.glicko_rating = rating.rating;
+        self.glicko_deviation = rating.deviation;
+        self.glicko_volatility = rating.volatility;
+    }
+}
+
 #[account]
 #[derive(InitSpace)]
+#[cfg_attr(test, derive(Default))]
 pub struct Battle {
     pub id: u64,
+    /// Paid for the battle account (and token escrow); gets the rent back on close
+    pub payer: Pubkey,
     pub challenger: Pubkey,
     pub opponent: Pubkey,
     #[max_len(256)]
     pub topic: String,
+    /// None = native SOL
+    pub mint: Option<Pubkey>,
+    pub open_terms: Option<OpenTerms>,
     pub status: BattleStatus,
     pub challenger_stake: u64,
     pub opponent_stake: u64,
@@ -669,13 +2695,38 @@ pub struct Battle {
     pub pool_opponent: u64,
     pub votes_challenger: u64,
     pub votes_opponent: u64,
+    pub votes_committed: u64,
+    /// Vote records closed so far; the battle can close once this reaches votes_committed
+    pub votes_closed: u64,
     pub total_bets: u64,
+    /// Bet accounts closed so far; the escrow can close once this reaches total_bets
+    pub bets_closed: u64,
+    pub escrow_closed: bool,
+    /// Set once update_glicko has rated both fighters for this (settled) battle
+    pub glicko_updated: bool,
     pub voting_period: i64,
+    pub reveal_period: i64,
+    pub claim_period: i64,
+    pub bet_cutoff: i64,
     pub created_at: i64,
+    pub acceptance_deadline: i64,
     pub accepted_at: Option<i64>,
     pub voting_ends_at: Option<i64>,
+    pub reveal_ends_at: Option<i64>,
+    pub betting_ends_at: Option<i64>,
     pub settled_at: Option<i64>,
-    pub winner: Option<BetSide>,
+    /// Deadline for claims and refunds, before pushing it back for claim pauses
+    pub claim_ends_at: Option<i64>,
+    /// Arena claim_pause_elapsed when the claim window opened
+    pub claim_pause_offset: i64,
+    pub winner: Option<BattleOutcome>,
+    /// Settlement snapshot: pools net of the house fee, to be split pro rata
+    pub prize_pool: u64,
+    pub house_fee: u64,
+    /// Pool the prize is split across (the winning side, or both on a draw)
+    pub winning_pool: u64,
+    /// Claimed from prize_pool so far
+    pub paid_out: u64,
     pub bump: u8,
 }
 
@@ -684,14 +2735,45 @@ pub struct Battle {
 pub struct Bet {
     pub battle: Pubkey,
     pub bettor: Pubkey,
+    /// Paid for the bet account; gets the rent back on close
+    pub payer: Pubkey,
     pub amount: u64,
     pub side: BetSide,
-    pub has_voted: bool,
     pub claimed: bool,
     pub placed_at: i64,
     pub bump: u8,
 }
 
+/// Per-mint allowlist entry and minimums for SPL battle pools
+#[account]
+#[derive(InitSpace)]
+pub struct MintConfig {
+    pub mint: Pubkey,
+    pub min_bet: u64,
+    pub min_stake_to_create: u64,
+    pub enabled: bool,
+    pub total_volume: u64,
+    pub total_fees_collected: u64,
+    pub total_paid_out: u64,
+    pub total_swept: u64,
+    pub bump: u8,
+}
+
+/// One per (battle, voting fighter); its existence prevents double voting
+#[account]
+#[derive(InitSpace)]
+pub struct VoteRecord {
+    pub battle: Pubkey,
+    pub voter: Pubkey,
+    /// Wallet that paid for the record, receives its rent when a revealed vote is closed
+    pub payer: Pubkey,
+    pub commitment: [u8; 32],
+    pub choice: Option<BetSide>,
+    pub committed_at: i64,
+    pub revealed_at: Option<i64>,
+    pub bump: u8,
+}
+
 // === TYPES ===
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
@@ -700,14 +2782,67 @@ pub struct ArenaConfig {
     pub min_bet: u64,
     pub min_stake_to_create: u64,
     pub voting_period: i64,
+    pub acceptance_period: i64,
+    pub reveal_period: i64,
+    pub claim_period: i64,
+    pub bet_cutoff: i64,
+    pub min_judge_battles: u32,
+    pub min_judge_elo: u32,
+    pub max_missed_reveals: u32,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
-pub enum BattleStatus {
-    Challenge,
-    Live,
-    Settled,
-    Cancelled,
+/// Delegate (session key) allowed to act for a fighter
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct SessionKey {
+    pub key: Pubkey,
+    /// Bitmask of DELEGATE_* flags
+    pub permissions: u8,
+    /// Cumulative lamports the delegate may stake from the vault
+    pub spend_limit: u64,
+    pub spent: u64,
+    pub expires_at: i64,
+}
+
+/// Profile fields to change in update_fighter (None = unchanged)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct FighterProfileUpdate {
+    pub name: Option<String>,
+    pub metadata_uri: Option<String>,
+    pub endpoint: Option<String>,
+    pub model: Option<String>,
+    pub avatar_hash: Option<[u8; 32]>,
+}
+
+/// Eligibility rules for an open challenge
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct OpenTerms {
+    pub min_elo: u32,
+    pub max_elo: u32,
+    pub min_stake: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace)]
+#[cfg_attr(test, derive(Default))]
+pub enum BattleStatus {
+    #[cfg_attr(test, default)]
+    Challenge,
+    Live,
+    Settled,
+    Cancelled,
+    Declined,
+    Expired,
+    /// Settled with an empty side: no counterparty, so every bet is refunded without a fee
+    Void,
+}
+
+impl BattleStatus {
+    /// No further stakes, bets, votes or settlement can happen
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            Self::Settled | Self::Cancelled | Self::Declined | Self::Expired | Self::Void
+        )
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Debug)]
@@ -716,6 +2851,350 @@ pub enum BetSide {
     Opponent,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, InitSpace, Debug)]
+pub enum BattleOutcome {
+    Challenger,
+    Opponent,
+    Draw,
+}
+
+// === EVENTS ===
+
+#[event]
+pub struct ArenaInitialized {
+    pub authority: Pubkey,
+    pub treasury: Pubkey,
+    pub house_fee_bps: u16,
+    pub min_bet: u64,
+    pub min_stake_to_create: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ArenaConfigUpdated {
+    pub authority: Pubkey,
+    pub treasury: Pubkey,
+    pub house_fee_bps: u16,
+    pub min_bet: u64,
+    pub min_stake_to_create: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ArenaMigrated {
+    pub authority: Pubkey,
+    /// Account size after the migration
+    pub size: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FighterMigrated {
+    pub fighter: Pubkey,
+    pub wallet: Pubkey,
+    /// Account size after the migration
+    pub size: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityProposed {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseUpdated {
+    pub paused: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MintConfigured {
+    pub mint: Pubkey,
+    pub min_bet: u64,
+    pub min_stake_to_create: u64,
+    pub enabled: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FighterRegistered {
+    pub fighter: Pubkey,
+    pub wallet: Pubkey,
+    pub name: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FighterUpdated {
+    pub fighter: Pubkey,
+    pub wallet: Pubkey,
+    pub name: String,
+    pub metadata_uri: String,
+    pub endpoint: String,
+    pub model: String,
+    pub avatar_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FighterRetired {
+    pub fighter: Pubkey,
+    pub wallet: Pubkey,
+    pub name: String,
+    pub elo: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub total_earnings: u64,
+    pub total_losses: u64,
+    pub registered_at: i64,
+    /// Lamports returned from the fighter vault
+    pub vault_refund: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VaultWithdrawn {
+    pub fighter: Pubkey,
+    pub wallet: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DelegateUpdated {
+    pub fighter: Pubkey,
+    /// None when revoked
+    pub delegate: Option<Pubkey>,
+    pub permissions: u8,
+    pub spend_limit: u64,
+    pub expires_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ChallengeIssued {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub challenger: Pubkey,
+    /// None for an open challenge
+    pub opponent: Option<Pubkey>,
+    pub mint: Option<Pubkey>,
+    pub stake: u64,
+    pub acceptance_deadline: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ChallengeAccepted {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub opponent: Pubkey,
+    pub stake: u64,
+    pub voting_ends_at: i64,
+    pub betting_ends_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ChallengeCancelled {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub challenger: Pubkey,
+    pub refunded_stake: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ChallengeDeclined {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub opponent: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ChallengeExpired {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RefundClaimed {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub bettor: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BetPlaced {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub bettor: Pubkey,
+    pub side: BetSide,
+    pub amount: u64,
+    pub pool_challenger: u64,
+    pub pool_opponent: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BetIncreased {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub bettor: Pubkey,
+    pub side: BetSide,
+    /// Amount added (net of any token transfer fee)
+    pub amount: u64,
+    /// Bet total after the increase
+    pub total: u64,
+    /// Amount-weighted average entry time
+    pub placed_at: i64,
+    pub pool_challenger: u64,
+    pub pool_opponent: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BetWithdrawn {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub bettor: Pubkey,
+    pub side: BetSide,
+    pub amount: u64,
+    pub pool_challenger: u64,
+    pub pool_opponent: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoteCommitted {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub voter: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoteUnrevealed {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub voter: Pubkey,
+    pub missed_reveals: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoteClosed {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub voter: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VoteCast {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub voter: Pubkey,
+    pub choice: BetSide,
+    pub votes_challenger: u64,
+    pub votes_opponent: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BattleVoided {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub pool_challenger: u64,
+    pub pool_opponent: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BattleSettled {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub winner: BattleOutcome,
+    pub votes_challenger: u64,
+    pub votes_opponent: u64,
+    pub total_pool: u64,
+    pub house_fee: u64,
+    pub challenger_elo: u32,
+    pub opponent_elo: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GlickoUpdated {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub challenger_rating: f64,
+    pub challenger_deviation: f64,
+    pub opponent_rating: f64,
+    pub opponent_deviation: f64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WinningsClaimed {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub bettor: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BetClosed {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub bettor: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EscrowClosed {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    /// Rounding remainder sent to the treasury
+    pub dust: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EscrowSwept {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub treasury: Pubkey,
+    /// Unclaimed winnings plus rounding dust
+    pub amount: u64,
+    /// Bets not yet closed at sweep time (unclaimed winners and uncranked losers)
+    pub open_bets: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BattleClosed {
+    pub battle_id: u64,
+    pub battle: Pubkey,
+    pub timestamp: i64,
+}
+
 // === ERRORS ===
 
 #[error_code]
@@ -756,16 +3235,803 @@ pub enum ArenaError {
     NotChallenger,
     #[msg("Cannot challenge yourself")]
     SameFighter,
+    #[msg("Battle is not refundable")]
+    BattleNotRefundable,
+    #[msg("Acceptance period must be positive")]
+    InvalidAcceptancePeriod,
+    #[msg("Challenge acceptance deadline has passed")]
+    ChallengeExpired,
+    #[msg("Challenge acceptance deadline not reached")]
+    ChallengeNotExpired,
+    #[msg("Provide either an opponent or open terms with a valid ELO range")]
+    InvalidOpenTerms,
+    #[msg("Fighter does not meet the open challenge terms")]
+    NotEligible,
+    #[msg("Battle participants cannot vote")]
+    ParticipantCannotVote,
+    #[msg("Reveal period must be positive")]
+    InvalidRevealPeriod,
+    #[msg("Reveal period ended")]
+    RevealEnded,
+    #[msg("Reveal period not ended")]
+    RevealNotEnded,
+    #[msg("Vote already revealed")]
+    AlreadyRevealed,
+    #[msg("Revealed vote does not match commitment")]
+    CommitmentMismatch,
+    #[msg("Mint is not allowed for battle pools")]
+    MintNotAllowed,
+    #[msg("Token accounts required for SPL battles")]
+    MissingTokenAccounts,
+    #[msg("Mint has a Token-2022 extension incompatible with escrow")]
+    UnsupportedMintExtension,
+    #[msg("House fee exceeds maximum")]
+    FeeTooHigh,
+    #[msg("Not the pending authority")]
+    NotPendingAuthority,
+    #[msg("Instruction is paused")]
+    Paused,
+    #[msg("Unknown pause flags")]
+    InvalidPauseFlags,
+    #[msg("URI exceeds 200 characters")]
+    UriTooLong,
+    #[msg("Model description exceeds 64 characters")]
+    ModelTooLong,
+    #[msg("Fighter has open battles")]
+    FighterHasActiveBattles,
+    #[msg("Signer is not the fighter wallet or an authorized delegate")]
+    NotAuthorized,
+    #[msg("Delegate has expired")]
+    DelegateExpired,
+    #[msg("Delegate spend limit exceeded")]
+    SpendLimitExceeded,
+    #[msg("Invalid delegate key, permissions or expiry")]
+    InvalidDelegate,
+    #[msg("Fighter vault account required")]
+    MissingVault,
+    #[msg("Bet still has a pending claim or its battle is not over")]
+    BetNotFinished,
+    #[msg("Battle is not over")]
+    BattleNotFinal,
+    #[msg("Bets are still open")]
+    BetsOutstanding,
+    #[msg("Vote records are still open")]
+    VotesOutstanding,
+    #[msg("Glicko-2 ratings were already updated for this battle")]
+    GlickoAlreadyUpdated,
+    #[msg("Run update_glicko before closing the battle")]
+    GlickoPending,
+    #[msg("Unrevealed votes are closed by flag_unrevealed")]
+    VoteNotRevealed,
+    #[msg("Escrow already closed")]
+    EscrowClosed,
+    #[msg("Escrow must be closed first")]
+    EscrowOpen,
+    #[msg("Claim period must be positive")]
+    InvalidClaimPeriod,
+    #[msg("Claim window has closed")]
+    ClaimWindowClosed,
+    #[msg("Claim window is still open")]
+    ClaimWindowOpen,
+    #[msg("Payout exceeds the settled prize pool or escrow's rent-exempt reserve")]
+    EscrowInsolvent,
+    #[msg("Bets can only be withdrawn before the challenge is accepted")]
+    BetLocked,
+    #[msg("Challenger stake is returned by cancelling the challenge")]
+    StakeNotWithdrawable,
+    #[msg("Stake bets cannot be increased")]
+    StakeBetFixed,
+    #[msg("Bet cutoff must be non-negative and shorter than the voting period")]
+    InvalidBetCutoff,
+    #[msg("Betting has closed for this battle")]
+    BettingClosed,
+    #[msg("Not an arena account")]
+    InvalidArenaAccount,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Not a fighter account for this wallet")]
+    InvalidFighterAccount,
+    #[msg("Signer is not the arena authority")]
+    NotArenaAuthority,
+    #[msg("Fighter does not meet the arena's judge requirements")]
+    NotEligibleJudge,
 }
 
 // === HELPERS ===
 
-fn calculate_new_elo(elo_a: u32, elo_b: u32, a_wins: bool) -> (u32, u32) {
-    let k: f64 = 32.0;
-    let expected_a = 1.0 / (1.0 + 10_f64.powf((elo_b as f64 - elo_a as f64) / 400.0));
-    let expected_b = 1.0 - expected_a;
-    let (score_a, score_b) = if a_wins { (1.0, 0.0) } else { (0.0, 1.0) };
-    let new_elo_a = (elo_a as f64 + k * (score_a - expected_a)).max(100.0) as u32;
-    let new_elo_b = (elo_b as f64 + k * (score_b - expected_b)).max(100.0) as u32;
-    (new_elo_a, new_elo_b)
+/// `outcome` is from fighter A's (challenger's) perspective
+fn calculate_new_elo(elo_a: u32, elo_b: u32, outcome: &BattleOutcome) -> (u32, u32) {
+    let score_a = match outcome {
+        BattleOutcome::Challenger => elo::WIN,
+        BattleOutcome::Opponent => elo::LOSS,
+        BattleOutcome::Draw => elo::DRAW,
+    };
+    elo::update(elo_a, elo_b, score_a)
+}
+
+fn validate_config(config: &ArenaConfig) -> Result<()> {
+    require!(config.house_fee_bps <= MAX_HOUSE_FEE_BPS, ArenaError::FeeTooHigh);
+    require!((300..=86400).contains(&config.voting_period), ArenaError::InvalidVotingPeriod);
+    require!(config.acceptance_period > 0, ArenaError::InvalidAcceptancePeriod);
+    require!(config.reveal_period > 0, ArenaError::InvalidRevealPeriod);
+    require!(config.claim_period > 0, ArenaError::InvalidClaimPeriod);
+    require!((0..config.voting_period).contains(&config.bet_cutoff), ArenaError::InvalidBetCutoff);
+    Ok(())
+}
+
+/// Send everything left in escrow to the treasury and close it, returning the SOL
+/// reserve or token account rent to the battle payer. Returns the amount swept.
+fn drain_escrow(accounts: &mut CloseEscrow, escrow_bump: u8) -> Result<u64> {
+    let arena = &mut accounts.arena;
+    let battle = &mut accounts.battle;
+    
+    let battle_key = battle.key();
+    let escrow_seeds = &[
+        b"escrow".as_ref(),
+        battle_key.as_ref(),
+        &[escrow_bump],
+    ];
+    
+    let reserve = Rent::get()?.minimum_balance(0);
+    let amount = match &accounts.escrow_token {
+        Some(escrow_token) if battle.mint.is_some() => escrow_token.amount,
+        None if battle.mint.is_some() => return err!(ArenaError::MissingTokenAccounts),
+        _ => accounts.escrow.lamports().saturating_sub(reserve),
+    };
+    
+    if amount > 0 {
+        payout_from_escrow(
+            battle.mint,
+            accounts.escrow.to_account_info(),
+            escrow_seeds,
+            accounts.treasury.to_account_info(),
+            accounts.system_program.to_account_info(),
+            TokenLeg::from_accounts(
+                &accounts.mint,
+                &accounts.treasury_token,
+                &accounts.escrow_token,
+                &accounts.token_program,
+            ),
+            amount,
+        )?;
+    }
+    
+    if let (Some(escrow_token), Some(token_program)) =
+        (&accounts.escrow_token, &accounts.token_program)
+    {
+        // Withheld transfer fees block close_account; harvesting them to the mint is permissionless
+        if withheld_fees(&escrow_token.to_account_info())? > 0 {
+            let mint = accounts.mint.as_ref().ok_or(ArenaError::MissingTokenAccounts)?;
+            let harvest = harvest_withheld_tokens_to_mint(
+                &token_program.key(),
+                &mint.key(),
+                &[&escrow_token.key()],
+            )?;
+            anchor_lang::solana_program::program::invoke(
+                &harvest,
+                &[
+                    mint.to_account_info(),
+                    escrow_token.to_account_info(),
+                    token_program.to_account_info(),
+                ],
+            )?;
+        }
+        
+        token_interface::close_account(CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token_interface::CloseAccount {
+                account: escrow_token.to_account_info(),
+                destination: accounts.payer.to_account_info(),
+                authority: accounts.escrow.to_account_info(),
+            },
+            &[escrow_seeds],
+        ))?;
+    } else if accounts.escrow.lamports() > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: accounts.escrow.to_account_info(),
+                    to: accounts.payer.to_account_info(),
+                },
+                &[escrow_seeds],
+            ),
+            accounts.escrow.lamports(),
+        )?;
+    }
+    
+    battle.escrow_closed = true;
+    record_stats(arena, &mut accounts.mint_config, battle.mint, 0, 0, 0, amount)?;
+    Ok(amount)
+}
+
+/// Check `signer` may act for `fighter`: its wallet, or an unexpired delegate holding
+/// `permission`. Lamports the delegate stakes from the vault count toward its spend limit.
+/// Returns true when the signer is the delegate.
+fn authorize_fighter(
+    fighter: &mut Fighter,
+    signer: Pubkey,
+    permission: u8,
+    vault_spend: u64,
+    now: i64,
+) -> Result<bool> {
+    if signer == fighter.wallet {
+        return Ok(false);
+    }
+    
+    let session = fighter.delegate.as_mut().ok_or(ArenaError::NotAuthorized)?;
+    require!(
+        session.key == signer && session.permissions & permission != 0,
+        ArenaError::NotAuthorized
+    );
+    require!(now < session.expires_at, ArenaError::DelegateExpired);
+    
+    session.spent = session.spent.saturating_add(vault_spend);
+    require!(session.spent <= session.spend_limit, ArenaError::SpendLimitExceeded);
+    Ok(true)
+}
+
+/// Account a fighter's SOL stake is drawn from: the signer, or the fighter vault PDA
+/// when a delegate signs
+fn stake_source<'a, 'info>(
+    use_vault: bool,
+    signer: &Signer<'info>,
+    vault: &Option<UncheckedAccount<'info>>,
+    vault_seeds: &'a [&'a [u8]],
+) -> Result<(AccountInfo<'info>, &'a [&'a [u8]])> {
+    if !use_vault {
+        return Ok((signer.to_account_info(), &[]));
+    }
+    let vault = vault.as_ref().ok_or(ArenaError::MissingVault)?;
+    Ok((vault.to_account_info(), vault_seeds))
+}
+
+/// Bets (new or increased) must meet the minimum for the battle's currency and land
+/// while the battle is open and betting hasn't closed
+fn check_bet_open(
+    arena: &Arena,
+    battle: &Battle,
+    mint_config: &Option<Box<Account<MintConfig>>>,
+    amount: u64,
+) -> Result<()> {
+    require!(!arena.is_paused(PAUSE_BET), ArenaError::Paused);
+    let min_bet = match (battle.mint, mint_config) {
+        (None, _) => arena.min_bet,
+        (Some(mint), Some(config)) => {
+            require!(config.mint == mint, ArenaError::MintNotAllowed);
+            config.min_bet
+        }
+        (Some(_), None) => return err!(ArenaError::MintNotAllowed),
+    };
+    require!(amount >= min_bet, ArenaError::BetTooSmall);
+    require!(
+        battle.status == BattleStatus::Challenge || battle.status == BattleStatus::Live,
+        ArenaError::BattleNotOpen
+    );
+    
+    if let Some(betting_ends) = battle.betting_ends_at {
+        require!(Clock::get()?.unix_timestamp < betting_ends, ArenaError::BettingClosed);
+    }
+    Ok(())
+}
+
+/// Whether a bet is a participant's stake, which only moves through the challenge lifecycle
+fn is_stake_bet(bet: &Bet, challenger_wallet: Pubkey, opponent_wallet: Option<Pubkey>) -> bool {
+    bet.bettor == challenger_wallet || Some(bet.bettor) == opponent_wallet
+}
+
+/// Move lamports out of a fighter vault, signing for the PDA
+fn transfer_from_vault<'info>(
+    fighter: Pubkey,
+    vault_bump: u8,
+    vault: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let vault_seeds = &[b"vault".as_ref(), fighter.as_ref(), &[vault_bump]];
+    anchor_lang::system_program::transfer(
+        CpiContext::new_with_signer(
+            system_program,
+            anchor_lang::system_program::Transfer { from: vault, to },
+            &[vault_seeds],
+        ),
+        amount,
+    )
+}
+
+/// Start the claim (or refund) window when a battle reaches a final status
+fn open_claim_window(arena: &Arena, battle: &mut Battle, now: i64) {
+    battle.claim_ends_at = Some(now + battle.claim_period);
+    battle.claim_pause_offset = arena.claim_pause_elapsed(now);
+}
+
+/// Claim deadline pushed back by however long claims were paused since the window opened
+fn claim_deadline(arena: &Arena, battle: &Battle, now: i64) -> Option<i64> {
+    battle
+        .claim_ends_at
+        .map(|ends| ends + arena.claim_pause_elapsed(now) - battle.claim_pause_offset)
+}
+
+/// Add the opponent's stake to the opponent pool, which may already hold bets from the challenge phase
+fn add_opponent_stake(battle: &mut Battle, stake: u64) {
+    battle.opponent_stake = stake;
+    battle.pool_opponent += stake;
+    battle.total_bets += 1;
+}
+
+/// Record winner, house fee and pools for claims to pay from; returns the house fee
+fn snapshot_settlement(battle: &mut Battle, winner: &BattleOutcome, house_fee_bps: u16) -> u64 {
+    let total_pool = battle.pool_challenger + battle.pool_opponent;
+    let house_fee = (total_pool * house_fee_bps as u64) / 10000;
+    battle.winner = Some(winner.clone());
+    battle.house_fee = house_fee;
+    battle.prize_pool = total_pool - house_fee;
+    battle.winning_pool = match winner {
+        BattleOutcome::Challenger => battle.pool_challenger,
+        BattleOutcome::Opponent => battle.pool_opponent,
+        BattleOutcome::Draw => total_pool,
+    };
+    battle.paid_out = 0;
+    house_fee
+}
+
+/// Pro-rata payout for `amount` bet on `side` of a settled battle, from the settlement
+/// snapshot, or None if that side lost
+fn payout_share(battle: &Battle, side: &BetSide, amount: u64) -> Option<u64> {
+    if !bet_wins(battle, side) {
+        return None;
+    }
+    Some((amount as u128 * battle.prize_pool as u128 / battle.winning_pool as u128) as u64)
+}
+
+/// Whether bets on `side` share the prize (false if they lost or the battle is unsettled)
+fn bet_wins(battle: &Battle, side: &BetSide) -> bool {
+    matches!(
+        (&battle.winner, side),
+        (Some(BattleOutcome::Draw), _)
+            | (Some(BattleOutcome::Challenger), BetSide::Challenger)
+            | (Some(BattleOutcome::Opponent), BetSide::Opponent)
+    )
+}
+
+/// Stats accrue on Arena for native SOL battles and on the mint's MintConfig
+/// for SPL battles, so amounts in different units never mix
+fn record_stats(
+    arena: &mut Arena,
+    mint_config: &mut Option<Box<Account<MintConfig>>>,
+    mint: Option<Pubkey>,
+    volume: u64,
+    fees: u64,
+    paid_out: u64,
+    swept: u64,
+) -> Result<()> {
+    match (mint, mint_config.as_deref_mut()) {
+        (None, _) => {
+            arena.total_volume += volume;
+            arena.total_fees_collected += fees;
+            arena.total_paid_out += paid_out;
+            arena.total_swept += swept;
+        }
+        (Some(_), Some(config)) => {
+            config.total_volume += volume;
+            config.total_fees_collected += fees;
+            config.total_paid_out += paid_out;
+            config.total_swept += swept;
+        }
+        (Some(_), None) => return err!(ArenaError::MintNotAllowed),
+    }
+    Ok(())
+}
+
+/// Take a withdrawn bet back out of the arena or per-mint volume
+fn reverse_volume(
+    arena: &mut Arena,
+    mint_config: &mut Option<Box<Account<MintConfig>>>,
+    mint: Option<Pubkey>,
+    volume: u64,
+) -> Result<()> {
+    match (mint, mint_config.as_deref_mut()) {
+        (None, _) => arena.total_volume = arena.total_volume.saturating_sub(volume),
+        (Some(mint), Some(config)) => {
+            require!(config.mint == mint, ArenaError::MintNotAllowed);
+            config.total_volume = config.total_volume.saturating_sub(volume);
+        }
+        (Some(_), None) => return err!(ArenaError::MintNotAllowed),
+    }
+    Ok(())
+}
+
+/// Token accounts a transfer needs when the battle is denominated in an SPL mint
+struct TokenLeg<'info> {
+    mint: AccountInfo<'info>,
+    decimals: u8,
+    user_token: AccountInfo<'info>,
+    escrow_token: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+}
+
+impl<'info> TokenLeg<'info> {
+    fn from_accounts(
+        mint: &Option<Box<InterfaceAccount<'info, Mint>>>,
+        user_token: &Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+        escrow_token: &Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+        token_program: &Option<Interface<'info, TokenInterface>>,
+    ) -> Option<Self> {
+        let mint = mint.as_ref()?;
+        Some(Self {
+            mint: mint.to_account_info(),
+            decimals: mint.decimals,
+            user_token: user_token.as_ref()?.to_account_info(),
+            escrow_token: escrow_token.as_ref()?.to_account_info(),
+            token_program: token_program.as_ref()?.to_account_info(),
+        })
+    }
+}
+
+/// Move funds from a signer (or a PDA, via `depositor_seeds`) into the battle escrow
+/// (lamports, or tokens for SPL battles)
+/// Returns the amount escrow actually received, which is less than `amount` for
+/// Token-2022 mints with a transfer fee.
+fn deposit_to_escrow<'info>(
+    mint: Option<Pubkey>,
+    depositor: AccountInfo<'info>,
+    depositor_seeds: &[&[u8]],
+    escrow: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_leg: Option<TokenLeg<'info>>,
+    amount: u64,
+) -> Result<u64> {
+    if mint.is_none() {
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                system_program,
+                anchor_lang::system_program::Transfer { from: depositor, to: escrow },
+                &[depositor_seeds],
+            ),
+            amount,
+        )?;
+        return Ok(amount);
+    }
+    
+    let leg = token_leg.ok_or(ArenaError::MissingTokenAccounts)?;
+    let balance_before = token_interface::accessor::amount(&leg.escrow_token)?;
+    token_interface::transfer_checked(
+        CpiContext::new(
+            leg.token_program,
+            token_interface::TransferChecked {
+                from: leg.user_token,
+                mint: leg.mint,
+                to: leg.escrow_token.clone(),
+                authority: depositor,
+            },
+        ),
+        amount,
+        leg.decimals,
+    )?;
+    let balance_after = token_interface::accessor::amount(&leg.escrow_token)?;
+    Ok(balance_after - balance_before)
+}
+
+/// Move funds out of the battle escrow, signed by the escrow PDA
+fn payout_from_escrow<'info>(
+    mint: Option<Pubkey>,
+    escrow: AccountInfo<'info>,
+    escrow_seeds: &[&[u8]],
+    recipient: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    token_leg: Option<TokenLeg<'info>>,
+    amount: u64,
+) -> Result<()> {
+    if mint.is_none() {
+        let remaining = escrow.lamports().checked_sub(amount).ok_or(ArenaError::EscrowInsolvent)?;
+        require!(
+            remaining >= Rent::get()?.minimum_balance(0),
+            ArenaError::EscrowInsolvent
+        );
+        return anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                system_program,
+                anchor_lang::system_program::Transfer { from: escrow, to: recipient },
+                &[escrow_seeds],
+            ),
+            amount,
+        );
+    }
+    
+    let leg = token_leg.ok_or(ArenaError::MissingTokenAccounts)?;
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            leg.token_program,
+            token_interface::TransferChecked {
+                from: leg.escrow_token,
+                mint: leg.mint,
+                to: leg.user_token,
+                authority: escrow,
+            },
+            &[escrow_seeds],
+        ),
+        amount,
+        leg.decimals,
+    )
+}
+
+/// Token-2022 mint extensions that keep escrow transfers under the program's control.
+/// Anything else is rejected, including extensions newer than the linked token crate:
+/// a transfer hook (14) or pausable config (26) could later fail every escrow transfer,
+/// and a permanent delegate or confidential transfers move or hide escrowed balances.
+/// Metadata and group extensions (18-23) are harmless but unknown to spl-token-2022 0.6,
+/// so creating the escrow token account for such a mint would fail.
+const ALLOWED_MINT_EXTENSIONS: [u16; 3] = [
+    ExtensionType::TransferFeeConfig as u16,
+    ExtensionType::MintCloseAuthority as u16,
+    ExtensionType::InterestBearingConfig as u16,
+];
+
+/// Reject Token-2022 extensions that would let tokens leave (or freeze) escrow
+/// outside the program's control, or hide balances from its accounting. Walks the raw
+/// TLV data so extension types unknown to the token crate are caught too.
+fn check_mint_extensions(mint: &AccountInfo) -> Result<()> {
+    check_extension_tlv(&mint.try_borrow_data()?)
+}
+
+fn check_extension_tlv(data: &[u8]) -> Result<()> {
+    const BASE_LEN: usize = 165; // extensions start after the padded base + account type byte
+    if data.len() <= BASE_LEN {
+        return Ok(());
+    }
+    
+    let mut offset = BASE_LEN + 1;
+    while offset + 4 <= data.len() {
+        let extension = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let len = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        if extension == ExtensionType::Uninitialized as u16 {
+            break;
+        }
+        require!(
+            ALLOWED_MINT_EXTENSIONS.contains(&extension),
+            ArenaError::UnsupportedMintExtension
+        );
+        offset += 4 + len;
+    }
+    Ok(())
+}
+
+/// Transfer fees withheld on a Token-2022 account, which must be harvested before it can close
+fn withheld_fees(token_account: &AccountInfo) -> Result<u64> {
+    let data = token_account.try_borrow_data()?;
+    let state = StateWithExtensions::<token_interface::spl_token_2022::state::Account>::unpack(&data)?;
+    Ok(state
+        .get_extension::<TransferFeeAmount>()
+        .map(|fees| u64::from(fees.withheld_amount))
+        .unwrap_or(0))
+}
+
+/// Majority of revealed votes wins; a tie, or fewer reveals than REVEAL_QUORUM_BPS of
+/// commitments, is a draw so withholding reveals can't swing a close tally
+fn tally_outcome(votes_challenger: u64, votes_opponent: u64, votes_committed: u64) -> BattleOutcome {
+    let revealed = votes_challenger + votes_opponent;
+    if revealed * 10_000 < votes_committed * REVEAL_QUORUM_BPS {
+        return BattleOutcome::Draw;
+    }
+    match votes_challenger.cmp(&votes_opponent) {
+        std::cmp::Ordering::Greater => BattleOutcome::Challenger,
+        std::cmp::Ordering::Less => BattleOutcome::Opponent,
+        std::cmp::Ordering::Equal => BattleOutcome::Draw,
+    }
+}
+
+fn vote_commitment(choice: &BetSide, salt: &[u8; 32], voter: &Pubkey) -> [u8; 32] {
+    let choice_byte = match choice {
+        BetSide::Challenger => 0u8,
+        BetSide::Opponent => 1u8,
+    };
+    anchor_lang::solana_program::hash::hashv(&[&[choice_byte], salt, voter.as_ref()]).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bet_by(bettor: Pubkey, amount: u64, side: BetSide) -> Bet {
+        Bet {
+            battle: Pubkey::new_unique(),
+            bettor,
+            payer: bettor,
+            amount,
+            side,
+            claimed: false,
+            placed_at: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn opponent_bets_before_acceptance_stay_in_the_pool() {
+        let mut battle = Battle {
+            challenger_stake: 100,
+            pool_challenger: 100,
+            total_bets: 1,
+            ..Default::default()
+        };
+        // Opponent-side bet placed during the challenge phase
+        battle.pool_opponent += 60;
+        battle.total_bets += 1;
+
+        add_opponent_stake(&mut battle, 100);
+        assert_eq!(battle.pool_opponent, 160);
+        assert_eq!(battle.total_bets, 3);
+
+        let house_fee = snapshot_settlement(&mut battle, &BattleOutcome::Opponent, 250);
+        assert_eq!(house_fee, 6);
+        assert_eq!(battle.prize_pool, 254);
+        assert_eq!(battle.winning_pool, 160);
+
+        let stake_payout = payout_share(&battle, &BetSide::Opponent, 100).unwrap();
+        let bet_payout = payout_share(&battle, &BetSide::Opponent, 60).unwrap();
+        assert!(stake_payout + bet_payout <= battle.prize_pool);
+        assert!(battle.prize_pool - (stake_payout + bet_payout) < 2);
+        assert_eq!(payout_share(&battle, &BetSide::Challenger, 100), None);
+    }
+
+    #[test]
+    fn stake_bets_cannot_be_increased() {
+        let challenger = Pubkey::new_unique();
+        let opponent = Pubkey::new_unique();
+        let stake = bet_by(challenger, 100, BetSide::Challenger);
+        let opponent_stake = bet_by(opponent, 100, BetSide::Opponent);
+        let side_bet = bet_by(Pubkey::new_unique(), 50, BetSide::Opponent);
+
+        assert!(is_stake_bet(&stake, challenger, None));
+        assert!(is_stake_bet(&opponent_stake, challenger, Some(opponent)));
+        assert!(!is_stake_bet(&opponent_stake, challenger, None));
+        assert!(!is_stake_bet(&side_bet, challenger, Some(opponent)));
+    }
+
+    /// Mint data with the given (extension type, value length) TLV entries
+    fn mint_with_extensions(extensions: &[(u16, u16)]) -> Vec<u8> {
+        let mut data = vec![0u8; 166];
+        data[165] = 1; // AccountType::Mint
+        for &(extension, len) in extensions {
+            data.extend_from_slice(&extension.to_le_bytes());
+            data.extend_from_slice(&len.to_le_bytes());
+            data.resize(data.len() + len as usize, 0);
+        }
+        data
+    }
+
+    fn judge(wins: u32, losses: u32, draws: u32, elo: u32) -> Fighter {
+        Fighter {
+            wins,
+            losses,
+            draws,
+            elo,
+            ..Default::default()
+        }
+    }
+
+    fn arena_requiring(min_judge_battles: u32, min_judge_elo: u32) -> Arena {
+        Arena {
+            min_judge_battles,
+            min_judge_elo,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fresh_fighter_cannot_judge() {
+        let arena = arena_requiring(5, 1000);
+        assert!(!arena.is_eligible_judge(&judge(0, 0, 0, 1200)));
+    }
+
+    #[test]
+    fn judge_needs_enough_battles_and_rating() {
+        let arena = arena_requiring(5, 1000);
+        assert!(!arena.is_eligible_judge(&judge(2, 1, 1, 1200)));
+        assert!(!arena.is_eligible_judge(&judge(1, 4, 0, 999)));
+        assert!(arena.is_eligible_judge(&judge(1, 3, 1, 1000)));
+    }
+
+    #[test]
+    fn zero_requirements_allow_any_fighter() {
+        assert!(arena_requiring(0, 0).is_eligible_judge(&judge(0, 0, 0, 1200)));
+    }
+
+    #[test]
+    fn judge_with_too_many_missed_reveals_is_ineligible() {
+        let arena = Arena {
+            max_missed_reveals: 1,
+            ..arena_requiring(0, 0)
+        };
+        let mut fighter = judge(3, 3, 0, 1200);
+        fighter.missed_reveals = 1;
+        assert!(arena.is_eligible_judge(&fighter));
+        fighter.missed_reveals = 2;
+        assert!(!arena.is_eligible_judge(&fighter));
+    }
+
+    #[test]
+    fn tally_uses_majority_when_quorum_is_met() {
+        assert!(matches!(tally_outcome(3, 1, 4), BattleOutcome::Challenger));
+        assert!(matches!(tally_outcome(1, 2, 6), BattleOutcome::Opponent));
+        assert!(matches!(tally_outcome(2, 2, 4), BattleOutcome::Draw));
+        assert!(matches!(tally_outcome(0, 0, 0), BattleOutcome::Draw));
+    }
+
+    #[test]
+    fn tally_is_a_draw_below_reveal_quorum() {
+        // 2 of 5 committed votes revealed: under 50%
+        assert!(matches!(tally_outcome(2, 0, 5), BattleOutcome::Draw));
+    }
+
+    #[test]
+    fn legacy_arena_deserializes_and_is_smaller_than_current() {
+        let legacy = LegacyArena {
+            authority: Pubkey::new_unique(),
+            treasury: Pubkey::new_unique(),
+            house_fee_bps: 500,
+            min_bet: 10_000_000,
+            min_stake_to_create: 100_000_000,
+            voting_period: 3600,
+            battle_count: 7,
+            total_volume: 42,
+            bump: 254,
+        };
+        let data = legacy.try_to_vec().unwrap();
+        assert!(8 + data.len() < 8 + Arena::INIT_SPACE);
+        let read = LegacyArena::deserialize(&mut &data[..]).unwrap();
+        assert_eq!((read.authority, read.battle_count, read.bump), (legacy.authority, 7, 254));
+    }
+
+    #[test]
+    fn legacy_fighter_deserializes_and_is_smaller_than_current() {
+        let legacy = LegacyFighter {
+            wallet: Pubkey::new_unique(),
+            name: "x".repeat(32),
+            elo: 1234,
+            wins: 5,
+            losses: 3,
+            draws: 1,
+            total_earnings: 42,
+            registered_at: 1_700_000_000,
+            bump: 253,
+        };
+        let data = legacy.try_to_vec().unwrap();
+        assert!(8 + data.len() < 8 + Fighter::INIT_SPACE);
+        let read = LegacyFighter::deserialize(&mut &data[..]).unwrap();
+        assert_eq!((read.wallet, read.elo, read.wins, read.bump), (legacy.wallet, 1234, 5, 253));
+    }
+
+    #[test]
+    fn plain_mint_is_allowed() {
+        assert!(check_extension_tlv(&[0u8; 82]).is_ok());
+    }
+
+    #[test]
+    fn allowed_extensions_pass() {
+        let data = mint_with_extensions(&[
+            (ExtensionType::TransferFeeConfig as u16, 108),
+            (ExtensionType::MintCloseAuthority as u16, 32),
+        ]);
+        assert!(check_extension_tlv(&data).is_ok());
+    }
+
+    #[test]
+    fn transfer_hook_is_rejected() {
+        let data = mint_with_extensions(&[(ExtensionType::MintCloseAuthority as u16, 32), (14, 64)]);
+        assert!(check_extension_tlv(&data).is_err());
+    }
+
+    #[test]
+    fn extensions_unknown_to_token_crate_are_rejected() {
+        // MetadataPointer: escrow account creation can't parse it with spl-token-2022 0.6
+        assert!(check_extension_tlv(&mint_with_extensions(&[(18, 64)])).is_err());
+    }
+
+    #[test]
+    fn permanent_delegate_and_unknown_extensions_are_rejected() {
+        let delegate = mint_with_extensions(&[(ExtensionType::PermanentDelegate as u16, 32)]);
+        assert!(check_extension_tlv(&delegate).is_err());
+        assert!(check_extension_tlv(&mint_with_extensions(&[(26, 33)])).is_err());
+    }
 }